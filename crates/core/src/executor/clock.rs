@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::task::{Poll, Waker};
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use spin::Mutex;
+
+pub trait Clock: Sync + Send {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+struct MockClockState {
+    now: Instant,
+    wakers: Vec<Waker>,
+}
+
+#[derive(Clone)]
+pub struct MockClock(Arc<Mutex<MockClockState>>);
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(MockClockState {
+            now: Instant::now(),
+            wakers: Vec::new(),
+        })))
+    }
+}
+
+impl MockClock {
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.0.lock();
+        state.now += duration;
+        state.wakers.drain(..).for_each(Waker::wake);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.0.lock().now
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        let state = self.0.clone();
+        let deadline = state.lock().now + duration;
+        Box::pin(futures_util::future::poll_fn(move |cx| {
+            let mut state = state.lock();
+            if state.now >= deadline {
+                Poll::Ready(())
+            } else {
+                state.wakers.push(cx.waker().clone());
+                Poll::Pending
+            }
+        }))
+    }
+}
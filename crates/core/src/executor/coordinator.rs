@@ -14,7 +14,16 @@ pub trait Coordinator: Sync + Send {
         service: &str,
         query: &str,
         variables: Variables,
+        headers: &[(String, String)],
     ) -> Result<Response, Self::Error>;
+
+    fn has_service(&self, _service: &str) -> bool {
+        true
+    }
+
+    fn is_retryable(&self, _err: &Self::Error) -> bool {
+        false
+    }
 }
 
 #[async_trait::async_trait]
@@ -26,7 +35,18 @@ impl<T: Coordinator> Coordinator for Arc<T> {
         service: &str,
         query: &str,
         variables: Variables,
+        headers: &[(String, String)],
     ) -> Result<Response, Self::Error> {
-        self.as_ref().query(service, query, variables).await
+        self.as_ref()
+            .query(service, query, variables, headers)
+            .await
+    }
+
+    fn has_service(&self, service: &str) -> bool {
+        self.as_ref().has_service(service)
+    }
+
+    fn is_retryable(&self, err: &Self::Error) -> bool {
+        self.as_ref().is_retryable(err)
     }
 }
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use indexmap::IndexMap;
+use spin::Mutex;
+
+use crate::{Clock, Response, TokioClock};
+
+#[async_trait::async_trait]
+pub trait FetchCache: Sync + Send {
+    async fn get(&self, key: u64) -> Option<Response>;
+
+    async fn put(&self, key: u64, response: Response, ttl: Duration);
+}
+
+#[async_trait::async_trait]
+impl<T: FetchCache> FetchCache for Arc<T> {
+    async fn get(&self, key: u64) -> Option<Response> {
+        self.as_ref().get(key).await
+    }
+
+    async fn put(&self, key: u64, response: Response, ttl: Duration) {
+        self.as_ref().put(key, response, ttl).await
+    }
+}
+
+pub struct MemoryFetchCache {
+    capacity: usize,
+    entries: Mutex<IndexMap<u64, (Instant, Response)>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryFetchCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+            clock: Arc::new(TokioClock),
+        }
+    }
+
+    pub fn clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl FetchCache for MemoryFetchCache {
+    async fn get(&self, key: u64) -> Option<Response> {
+        let mut entries = self.entries.lock();
+        let (expires_at, response) = entries.shift_remove(&key)?;
+        if expires_at <= self.clock.now() {
+            return None;
+        }
+        entries.insert(key, (expires_at, response.clone()));
+        Some(response)
+    }
+
+    async fn put(&self, key: u64, response: Response, ttl: Duration) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        entries.shift_remove(&key);
+        if entries.len() >= self.capacity {
+            entries.shift_remove_index(0);
+        }
+        entries.insert(key, (self.clock.now() + ttl, response));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+    use value::value;
+
+    use super::*;
+    use crate::MockClock;
+
+    fn response(data: i32) -> Response {
+        Response {
+            data: value!({ "a": data }),
+            errors: Vec::new(),
+            extensions: Default::default(),
+        }
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let cache = MemoryFetchCache::new(2);
+        let ttl = Duration::from_secs(60);
+        async {
+            cache.put(1, response(1), ttl).await;
+            cache.put(2, response(2), ttl).await;
+            assert!(cache.get(1).await.is_some());
+            cache.put(3, response(3), ttl).await;
+
+            assert_eq!(cache.get(1).await.unwrap().data, value!({ "a": 1 }));
+            assert!(cache.get(2).await.is_none());
+            assert_eq!(cache.get(3).await.unwrap().data, value!({ "a": 3 }));
+        }
+        .now_or_never()
+        .unwrap();
+    }
+
+    #[test]
+    fn expired_entries() {
+        let clock = MockClock::default();
+        let cache = MemoryFetchCache::new(2).clock(clock.clone());
+        async {
+            cache.put(1, response(1), Duration::from_secs(60)).await;
+            clock.advance(Duration::from_secs(59));
+            assert_eq!(cache.get(1).await.unwrap().data, value!({ "a": 1 }));
+            clock.advance(Duration::from_secs(1));
+            assert!(cache.get(1).await.is_none());
+        }
+        .now_or_never()
+        .unwrap();
+    }
+}
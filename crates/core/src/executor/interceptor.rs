@@ -0,0 +1,14 @@
+use crate::Response;
+
+pub trait ResponseInterceptor: Sync + Send {
+    fn on_subgraph_response(&self, service: &str, resp: &mut Response);
+}
+
+impl<F> ResponseInterceptor for F
+where
+    F: Fn(&str, &mut Response) + Sync + Send,
+{
+    fn on_subgraph_response(&self, service: &str, resp: &mut Response) {
+        self(service, resp)
+    }
+}
@@ -5,15 +5,18 @@ use crate::planner::IntrospectionSelectionSet;
 use crate::schema::ConstValue;
 use crate::ComposedSchema;
 
-pub struct IntrospectionRoot;
+pub struct IntrospectionRoot<'a> {
+    pub root_type: &'a str,
+}
 
-impl Resolver for IntrospectionRoot {
+impl<'a> Resolver for IntrospectionRoot<'a> {
     fn resolve(
         &self,
         selection_set: &IntrospectionSelectionSet,
         schema: &ComposedSchema,
     ) -> ConstValue {
         resolve_obj(selection_set, |name, field| match name {
+            "__typename" => ConstValue::String(self.root_type.to_string()),
             "__schema" => IntrospectionSchema.resolve(&field.selection_set, schema),
             "__type" => {
                 if let Some(ConstValue::String(name)) = field.arguments.get("name") {
@@ -27,3 +30,164 @@ impl Resolver for IntrospectionRoot {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_query;
+    use value::{value, Name, Variables};
+
+    use super::*;
+    use crate::planner::{PlanNode, SequenceNode};
+    use crate::PlanBuilder;
+
+    const TYPE_QUERY: &str = "query($n: String!) { __type(name: $n) { name } }";
+
+    fn resolve(schema: &ComposedSchema, query: &str, variables: Variables) -> Option<ConstValue> {
+        let document = parse_query(query).unwrap();
+        let plan = PlanBuilder::new(schema, document)
+            .variables(variables)
+            .plan()
+            .ok()?;
+        match plan {
+            PlanNode::Sequence(SequenceNode { nodes }) => match nodes.first() {
+                Some(PlanNode::Introspection(introspection)) => Some(
+                    IntrospectionRoot {
+                        root_type: &introspection.root_type,
+                    }
+                    .resolve(&introspection.selection_set, schema),
+                ),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn type_name_from_variable() {
+        let schema = ComposedSchema::parse("type Query { a: Int }").unwrap();
+
+        let mut variables = Variables::default();
+        variables.insert(Name::new("n"), value!("Query"));
+        assert_eq!(
+            resolve(&schema, TYPE_QUERY, variables),
+            Some(value!({ "__type": { "name": "Query" } }))
+        );
+
+        assert_eq!(resolve(&schema, TYPE_QUERY, Variables::default()), None);
+    }
+
+    #[test]
+    fn descriptions() {
+        let schema = ComposedSchema::parse(
+            r#"type Query {
+                "The answer."
+                a: Int
+                b: Int
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolve(
+                &schema,
+                r#"{ __schema { description } __type(name: "Query") { fields { name description } } }"#,
+                Variables::default(),
+            ),
+            Some(value!({
+                "__schema": { "description": null },
+                "__type": {
+                    "fields": [
+                        { "name": "a", "description": "The answer." },
+                        { "name": "b", "description": null }
+                    ]
+                }
+            }))
+        );
+    }
+
+    #[test]
+    fn root_typename() {
+        let schema = ComposedSchema::parse(
+            r#"schema { query: Query mutation: Mutation }
+            type Query { a: Int }
+            type Mutation { b: Int }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolve(&schema, "query { __typename }", Variables::default()),
+            Some(value!({ "__typename": "Query" }))
+        );
+        assert_eq!(
+            resolve(&schema, "mutation { __typename }", Variables::default()),
+            Some(value!({ "__typename": "Mutation" }))
+        );
+    }
+
+    #[test]
+    fn schema_types_are_sorted() {
+        let sdl = "type Query { b: B a: A } type B { id: ID } type A { id: ID } enum C { X }";
+        let names = || {
+            let schema = ComposedSchema::parse(sdl).unwrap();
+            resolve(
+                &schema,
+                "{ __schema { types { name } } }",
+                Variables::default(),
+            )
+        };
+        assert_eq!(
+            names(),
+            Some(value!({
+                "__schema": {
+                    "types": [
+                        { "name": "A" },
+                        { "name": "B" },
+                        { "name": "Boolean" },
+                        { "name": "C" },
+                        { "name": "Float" },
+                        { "name": "ID" },
+                        { "name": "Int" },
+                        { "name": "Query" },
+                        { "name": "String" },
+                    ]
+                }
+            }))
+        );
+        assert_eq!(names(), names());
+    }
+
+    #[test]
+    fn input_field_default_values() {
+        let schema = ComposedSchema::parse(
+            r#"type Query { a(input: MyInput): Int }
+            enum Color { RED GREEN }
+            input MyInput {
+                count: Int = 10
+                label: String = "hello \"world\""
+                color: Color = RED
+                enabled: Boolean = false
+                note: String
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            resolve(
+                &schema,
+                r#"{ __type(name: "MyInput") { inputFields { name defaultValue } } }"#,
+                Variables::default(),
+            ),
+            Some(value!({
+                "__type": {
+                    "inputFields": [
+                        { "name": "count", "defaultValue": "10" },
+                        { "name": "label", "defaultValue": "\"hello \\\"world\\\"\"" },
+                        { "name": "color", "defaultValue": "RED" },
+                        { "name": "enabled", "defaultValue": "false" },
+                        { "name": "note", "defaultValue": null }
+                    ]
+                }
+            }))
+        );
+    }
+}
@@ -14,14 +14,23 @@ impl Resolver for IntrospectionSchema {
         schema: &ComposedSchema,
     ) -> ConstValue {
         resolve_obj(selection_set, |name, field| match name {
-            "types" => ConstValue::List(
-                schema
+            "description" => ConstValue::Null,
+            "types" => {
+                let mut types = schema
                     .types
                     .values()
                     .filter(|ty| !ty.name.starts_with("__"))
-                    .map(|ty| IntrospectionType::Named(ty).resolve(&field.selection_set, schema))
-                    .collect(),
-            ),
+                    .collect::<Vec<_>>();
+                types.sort_by(|a, b| a.name.cmp(&b.name));
+                ConstValue::List(
+                    types
+                        .into_iter()
+                        .map(|ty| {
+                            IntrospectionType::Named(ty).resolve(&field.selection_set, schema)
+                        })
+                        .collect(),
+                )
+            }
             "queryType" => {
                 let query_type = schema
                     .types
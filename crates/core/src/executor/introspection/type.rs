@@ -84,7 +84,7 @@ impl<'a> Resolver for IntrospectionType<'a> {
                     ConstValue::List(
                         ty.fields
                             .values()
-                            .filter(|item| !item.name.starts_with("__"))
+                            .filter(|item| !item.name.starts_with("__") && !item.inaccessible)
                             .filter(|item| {
                                 if is_include_deprecated(&field.arguments) {
                                     true
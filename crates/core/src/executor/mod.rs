@@ -1,27 +1,71 @@
+mod clock;
 mod coordinator;
+mod fetch_cache;
+mod interceptor;
 mod introspection;
+mod options;
 mod response;
+mod retry_budget;
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use futures_util::future::BoxFuture;
+use futures_util::future::{BoxFuture, Either};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use parser::types::{BaseType, Type};
 use spin::Mutex;
-use tracing::instrument;
+use tokio::sync::watch;
+use tracing::{instrument, Span};
 use value::{ConstValue, Name, Variables};
 
+pub use clock::{Clock, MockClock, TokioClock};
 pub use coordinator::Coordinator;
-pub use response::{ErrorPath, Response, ServerError};
+pub use fetch_cache::{FetchCache, MemoryFetchCache};
+pub use interceptor::ResponseInterceptor;
+pub use options::{ExecutorOptions, ListMergePolicy, RetryPolicy, ServiceOptions};
+pub use response::{ErrorPath, IncrementalResponse, Response, ServerError};
+pub use retry_budget::RetryBudget;
 
 use crate::planner::{
-    FetchNode, FlattenNode, IntrospectionNode, ParallelNode, PathSegment, PlanNode, SequenceNode,
+    key_prefix, AliasedField, ComputeField, ComputeNode, FetchNode, FlattenNode, IntrospectionNode,
+    IntrospectionSelectionSet, NullCheckNode, ParallelNode, PathSegment, PlanNode, Projection,
+    ResponseField, ResponsePath, SequenceNode, KEY_PREFIX,
 };
 use crate::ComposedSchema;
 use introspection::{IntrospectionRoot, Resolver};
 
+const REQUEST_BODY_OVERHEAD: usize = 64;
+const MAX_RESPONSE_DEPTH: usize = 256;
+const DEFAULT_MAX_INTROSPECTION_DEPTH: usize = 15;
+
+type InFlightResult = Option<Result<Response, String>>;
+
+struct QueryError {
+    message: String,
+    retryable: bool,
+}
+
 pub struct Executor<'e, T> {
     schema: &'e ComposedSchema,
     resp: Mutex<Response>,
     coordinator: T,
+    options: ExecutorOptions,
+    headers: Vec<(String, String)>,
+    clock: Arc<dyn Clock>,
+    interceptors: Vec<Box<dyn ResponseInterceptor>>,
+    fetch_cache: Option<Arc<dyn FetchCache>>,
+    retry_budget: Option<Arc<RetryBudget>>,
+    request_bytes: AtomicUsize,
+    request_bytes_exceeded: AtomicBool,
+    representations: AtomicUsize,
+    representations_exceeded: AtomicBool,
+    in_flight: Mutex<HashMap<u64, (watch::Receiver<InFlightResult>, usize)>>,
 }
 
 impl<'e, T: Coordinator> Executor<'e, T> {
@@ -30,17 +74,205 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             schema,
             resp: Mutex::new(Response {
                 data: ConstValue::Null,
+                extensions: Default::default(),
                 errors: Vec::new(),
             }),
             coordinator,
+            options: Default::default(),
+            headers: Vec::new(),
+            clock: Arc::new(TokioClock),
+            interceptors: Vec::new(),
+            fetch_cache: None,
+            retry_budget: None,
+            request_bytes: AtomicUsize::new(0),
+            request_bytes_exceeded: AtomicBool::new(false),
+            representations: AtomicUsize::new(0),
+            representations_exceeded: AtomicBool::new(false),
+            in_flight: Default::default(),
+        }
+    }
+
+    pub fn options(self, options: ExecutorOptions) -> Self {
+        Self { options, ..self }
+    }
+
+    pub fn headers(self, headers: Vec<(String, String)>) -> Self {
+        Self { headers, ..self }
+    }
+
+    pub fn clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    pub fn interceptor(mut self, interceptor: impl ResponseInterceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    pub fn fetch_cache(self, fetch_cache: impl FetchCache + 'static) -> Self {
+        Self {
+            fetch_cache: Some(Arc::new(fetch_cache)),
+            ..self
         }
     }
 
-    pub async fn execute(self, node: &PlanNode<'_>) -> Response {
-        self.execute_node(node).await;
+    pub fn retry_budget(self, retry_budget: Arc<RetryBudget>) -> Self {
+        Self {
+            retry_budget: Some(retry_budget),
+            ..self
+        }
+    }
+
+    pub async fn execute(mut self, node: &PlanNode<'_>) -> Response {
+        self.filter_headers();
+        if let Some(response) = self.unconfigured_services(node) {
+            return response;
+        }
+
+        let deadline = self.deadline();
+        self.execute_node_until(node, deadline).await;
         self.resp.into_inner()
     }
 
+    pub fn execute_stream<'a>(
+        mut self,
+        node: &'a PlanNode<'_>,
+    ) -> BoxStream<'a, IncrementalResponse>
+    where
+        'e: 'a,
+        T: 'a,
+    {
+        self.filter_headers();
+        let (primary, deferred) = match node {
+            PlanNode::Defer(defer) => (&*defer.primary, defer.deferred.as_slice()),
+            _ => (node, &[][..]),
+        };
+
+        let deadline = self.deadline();
+        futures_util::stream::unfold(Some((self, 0)), move |state| async move {
+            let (executor, idx) = state?;
+            let (label, path, completed) = if idx == 0 {
+                if let Some(response) = executor.unconfigured_services(node) {
+                    let response = IncrementalResponse {
+                        response,
+                        label: None,
+                        path: None,
+                        has_next: false,
+                    };
+                    return Some((response, None));
+                }
+                let completed = executor.execute_node_until(primary, deadline).await;
+                (None, None, completed)
+            } else {
+                let deferred = &deferred[idx - 1];
+                let completed = executor.execute_node_until(&deferred.node, deadline).await;
+                (
+                    deferred.label.as_deref().map(ToString::to_string),
+                    Some(Vec::new()),
+                    completed,
+                )
+            };
+
+            let has_next = completed && idx < deferred.len();
+            let response = IncrementalResponse {
+                response: executor.take_response(),
+                label,
+                path,
+                has_next,
+            };
+            let next_state = if has_next {
+                Some((executor, idx + 1))
+            } else {
+                None
+            };
+            Some((response, next_state))
+        })
+        .boxed()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.options
+            .total_timeout
+            .map(|total_timeout| self.clock.now() + total_timeout)
+    }
+
+    async fn execute_node_until(&self, node: &PlanNode<'_>, deadline: Option<Instant>) -> bool {
+        let deadline = match deadline {
+            Some(deadline) => deadline,
+            None => {
+                self.execute_node(node).await;
+                return true;
+            }
+        };
+        let remaining = deadline.saturating_duration_since(self.clock.now());
+        let fut = self.execute_node(node);
+        if let Either::Left(_) =
+            futures_util::future::select(fut, self.clock.sleep(remaining)).await
+        {
+            return true;
+        }
+        let mut resp = self.resp.lock();
+        remove_key_fields(&mut resp.data);
+        resp.errors.push(ServerError {
+            message: format!(
+                "Operation exceeded {} ms.",
+                self.options.total_timeout.unwrap_or_default().as_millis()
+            ),
+            locations: Default::default(),
+            path: Default::default(),
+            extensions: Default::default(),
+        });
+        false
+    }
+
+    fn filter_headers(&mut self) {
+        let forward_headers = &self.options.forward_headers;
+        self.headers.retain(|(name, _)| {
+            forward_headers
+                .iter()
+                .any(|forward_header| forward_header.eq_ignore_ascii_case(name))
+        });
+    }
+
+    fn unconfigured_services(&self, node: &PlanNode<'_>) -> Option<Response> {
+        let errors = node
+            .services()
+            .into_iter()
+            .filter(|service| !self.coordinator.has_service(service))
+            .map(|service| ServerError {
+                message: format!(
+                    "Subgraph '{}' referenced by the plan is not configured.",
+                    service
+                ),
+                locations: Default::default(),
+                path: Default::default(),
+                extensions: Default::default(),
+            })
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            return None;
+        }
+        Some(Response {
+            data: ConstValue::Null,
+            errors,
+            extensions: Default::default(),
+        })
+    }
+
+    fn take_response(&self) -> Response {
+        std::mem::replace(
+            &mut *self.resp.lock(),
+            Response {
+                data: ConstValue::Null,
+                extensions: Default::default(),
+                errors: Vec::new(),
+            },
+        )
+    }
+
     fn execute_node<'a>(&'a self, node: &'a PlanNode<'_>) -> BoxFuture<'a, ()> {
         Box::pin(async move {
             match node {
@@ -51,6 +283,14 @@ impl<'e, T: Coordinator> Executor<'e, T> {
                 }
                 PlanNode::Fetch(fetch) => self.execute_fetch_node(fetch).await,
                 PlanNode::Flatten(flatten) => self.execute_flatten_node(flatten).await,
+                PlanNode::Compute(compute) => self.execute_compute_node(compute),
+                PlanNode::NullCheck(null_check) => self.execute_null_check_node(null_check),
+                PlanNode::Defer(defer) => {
+                    self.execute_node(&defer.primary).await;
+                    for deferred in &defer.deferred {
+                        self.execute_node(&deferred.node).await;
+                    }
+                }
             }
         })
     }
@@ -64,49 +304,194 @@ impl<'e, T: Coordinator> Executor<'e, T> {
 
     #[instrument(skip(self), level = "debug")]
     async fn execute_parallel_node(&self, parallel: &ParallelNode<'_>) {
-        futures_util::future::join_all(
-            parallel
-                .nodes
-                .iter()
-                .map(|node| async move { self.execute_node(node).await }),
-        )
-        .await;
+        let nodes = parallel
+            .nodes
+            .iter()
+            .map(|node| async move { self.execute_node(node).await });
+        match self.options.max_concurrency {
+            Some(max_concurrency) => {
+                futures_util::stream::iter(nodes)
+                    .buffer_unordered(max_concurrency.max(1))
+                    .collect::<Vec<_>>()
+                    .await;
+            }
+            None => {
+                futures_util::future::join_all(nodes).await;
+            }
+        }
     }
 
     #[instrument(skip(self), level = "debug")]
     fn execute_introspection_node(&self, introspection: &IntrospectionNode) {
-        let value = IntrospectionRoot.resolve(&introspection.selection_set, self.schema);
+        if !self.options.introspection_enabled
+            && introspection
+                .selection_set
+                .0
+                .iter()
+                .any(|field| field.name.as_str() != "__typename")
+        {
+            self.resp.lock().errors.push(ServerError {
+                message: "Introspection is disabled.".to_string(),
+                locations: Default::default(),
+                path: Default::default(),
+                extensions: Default::default(),
+            });
+            return;
+        }
+
+        let max_depth = self
+            .options
+            .max_introspection_depth
+            .unwrap_or(DEFAULT_MAX_INTROSPECTION_DEPTH);
+        if introspection_depth(&introspection.selection_set) > max_depth {
+            self.resp.lock().errors.push(ServerError {
+                message: format!(
+                    "Introspection query exceeds the maximum depth of {}.",
+                    max_depth
+                ),
+                locations: Default::default(),
+                path: Default::default(),
+                extensions: Default::default(),
+            });
+            return;
+        }
+
+        let value = IntrospectionRoot {
+            root_type: &introspection.root_type,
+        }
+        .resolve(&introspection.selection_set, self.schema);
         let mut current_resp = self.resp.lock();
-        merge_data(&mut current_resp.data, value);
+        merge_data(
+            &mut current_resp.data,
+            value,
+            self.options.list_merge_policy,
+        );
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        skip(self),
+        fields(
+            otel.kind = "client",
+            graphgate.service = %fetch.service,
+            graphgate.duration_ms = tracing::field::Empty,
+        ),
+        level = "debug"
+    )]
     async fn execute_fetch_node(&self, fetch: &FetchNode<'_>) {
-        let res = self
-            .coordinator
-            .query(fetch.service, &fetch.query, Default::default())
-            .await;
+        let start = self.clock.now();
+        let cache = match (&self.fetch_cache, fetch.cache_ttl) {
+            (Some(fetch_cache), Some(ttl)) if !fetch.is_mutation => {
+                let mut hasher = DefaultHasher::new();
+                request_key(&fetch.service, &fetch.query, &Variables::default()).hash(&mut hasher);
+                self.headers.hash(&mut hasher);
+                Some((fetch_cache, ttl, hasher.finish()))
+            }
+            _ => None,
+        };
+        let cached = match &cache {
+            Some((fetch_cache, _, key)) => fetch_cache.get(*key).await,
+            None => None,
+        };
+        let res = match cached {
+            Some(resp) => {
+                tracing::debug!(service = %fetch.service, "Fetch cache hit");
+                Ok(resp)
+            }
+            None => {
+                if !self.reserve_request_bytes(&fetch.query, &Variables::default()) {
+                    return;
+                }
+                let res = self
+                    .query_service(
+                        &fetch.service,
+                        &fetch.query,
+                        Default::default(),
+                        !fetch.is_mutation,
+                    )
+                    .await;
+                if let (Some((fetch_cache, ttl, key)), Ok(resp)) = (&cache, &res) {
+                    if resp.errors.is_empty() {
+                        fetch_cache.put(*key, resp.clone(), *ttl).await;
+                    }
+                }
+                res
+            }
+        }
+        .map(|resp| self.intercept_response(&fetch.service, resp));
+        record_duration(self.clock.now().saturating_duration_since(start));
         let mut current_resp = self.resp.lock();
 
         match res {
-            Ok(resp) => {
-                if resp.errors.is_empty() {
-                    merge_data(&mut current_resp.data, resp.data);
+            Ok(mut resp) => {
+                resp.data = split_aliases(std::mem::take(&mut resp.data), &fetch.aliases);
+                let has_errors = !resp.errors.is_empty();
+                if has_errors {
+                    self.report_errors(&mut current_resp.errors, &fetch.service, resp.errors);
+                }
+                if matches!(resp.data, ConstValue::Null) {
+                    if !has_errors && !self.options.allow_empty_responses {
+                        self.report_errors(
+                            &mut current_resp.errors,
+                            &fetch.service,
+                            vec![ServerError {
+                                message: format!(
+                                    "Subgraph '{}' returned neither data nor errors.",
+                                    fetch.service
+                                ),
+                                locations: Default::default(),
+                                path: fetch_error_path(fetch),
+                                extensions: Default::default(),
+                            }],
+                        );
+                    }
+                } else if exceeds_depth(&resp.data, MAX_RESPONSE_DEPTH) {
+                    current_resp
+                        .errors
+                        .push(response_too_deep_error(&fetch.service));
                 } else {
-                    merge_errors(&mut current_resp.errors, resp.errors);
+                    if self.options.expose_sources {
+                        if let ConstValue::Object(object) = &resp.data {
+                            let paths = object.keys().map(ToString::to_string).collect::<Vec<_>>();
+                            record_sources(&mut current_resp, &fetch.service, paths);
+                        }
+                    }
+                    if !merge_data(
+                        &mut current_resp.data,
+                        resp.data,
+                        self.options.list_merge_policy,
+                    ) {
+                        current_resp
+                            .errors
+                            .push(list_length_mismatch_error(&fetch.service));
+                    }
                 }
             }
-            Err(err) => current_resp.errors.push(ServerError {
-                message: err.to_string(),
-                locations: Default::default(),
-            }),
+            Err(err) => self.report_errors(
+                &mut current_resp.errors,
+                &fetch.service,
+                vec![ServerError {
+                    message: err.to_string(),
+                    locations: Default::default(),
+                    path: fetch_error_path(fetch),
+                    extensions: Default::default(),
+                }],
+            ),
         }
     }
 
-    #[instrument(skip(self), level = "debug")]
+    #[instrument(
+        skip(self),
+        fields(
+            otel.kind = "client",
+            graphgate.service = %flatten.service,
+            graphgate.representations.count = tracing::field::Empty,
+            graphgate.duration_ms = tracing::field::Empty,
+        ),
+        level = "debug"
+    )]
     async fn execute_flatten_node(&self, flatten: &FlattenNode<'_>) {
         fn extract_keys(from: &mut BTreeMap<Name, ConstValue>, prefix: usize) -> ConstValue {
-            let prefix = format!("__key{}_", prefix);
+            let prefix = key_prefix(prefix);
             let mut res = BTreeMap::new();
             let mut keys = Vec::new();
             for key in from.keys() {
@@ -125,6 +510,8 @@ impl<'e, T: Coordinator> Executor<'e, T> {
 
         fn get_representations(
             representations: &mut Vec<ConstValue>,
+            entity_paths: &mut Vec<Vec<ErrorPath>>,
+            current_path: &mut Vec<ErrorPath>,
             value: &mut ConstValue,
             path: &[PathSegment<'_>],
             prefix: usize,
@@ -135,18 +522,33 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             };
             let is_last = path.len() == 1;
 
+            current_path.push(ErrorPath::Name(segment.name.to_string()));
             if is_last {
                 match value {
                     ConstValue::Object(object) if !segment.is_list => {
-                        if let Some(ConstValue::Object(key_object)) = object.get_mut(segment.name) {
-                            representations.push(extract_keys(key_object, prefix));
+                        if let Some(ConstValue::Object(key_object)) =
+                            object.get_mut(segment.name.as_ref())
+                        {
+                            if is_possible_type(key_object, segment.possible_type.as_deref()) {
+                                representations.push(extract_keys(key_object, prefix));
+                                entity_paths.push(current_path.clone());
+                            }
                         }
                     }
                     ConstValue::Object(object) if segment.is_list => {
-                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name) {
-                            for element in array {
+                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name.as_ref())
+                        {
+                            for (idx, element) in array.iter_mut().enumerate() {
                                 if let ConstValue::Object(element_obj) = element {
-                                    representations.push(extract_keys(element_obj, prefix));
+                                    if is_possible_type(
+                                        element_obj,
+                                        segment.possible_type.as_deref(),
+                                    ) {
+                                        representations.push(extract_keys(element_obj, prefix));
+                                        current_path.push(ErrorPath::Index(idx));
+                                        entity_paths.push(current_path.clone());
+                                        current_path.pop();
+                                    }
                                 }
                             }
                         }
@@ -156,20 +558,61 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             } else {
                 match value {
                     ConstValue::Object(object) if !segment.is_list => {
-                        if let Some(next_value) = object.get_mut(segment.name) {
-                            get_representations(representations, next_value, &path[1..], prefix);
+                        if let Some(next_value) = object.get_mut(segment.name.as_ref()) {
+                            if is_possible_value(next_value, segment.possible_type.as_deref()) {
+                                get_representations(
+                                    representations,
+                                    entity_paths,
+                                    current_path,
+                                    next_value,
+                                    &path[1..],
+                                    prefix,
+                                );
+                            }
                         }
                     }
                     ConstValue::Object(object) if segment.is_list => {
-                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name) {
-                            for element in array {
-                                get_representations(representations, element, &path[1..], prefix);
+                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name.as_ref())
+                        {
+                            for (idx, element) in
+                                array.iter_mut().enumerate().filter(|(_, element)| {
+                                    is_possible_value(element, segment.possible_type.as_deref())
+                                })
+                            {
+                                current_path.push(ErrorPath::Index(idx));
+                                get_representations(
+                                    representations,
+                                    entity_paths,
+                                    current_path,
+                                    element,
+                                    &path[1..],
+                                    prefix,
+                                );
+                                current_path.pop();
                             }
                         }
                     }
                     _ => {}
                 }
             }
+            current_path.pop();
+        }
+
+        fn merge_entity(
+            target: &mut ConstValue,
+            value: ConstValue,
+            response_keys: &[Cow<'_, str>],
+            policy: ListMergePolicy,
+        ) -> bool {
+            match (target, value) {
+                (ConstValue::Object(object), ConstValue::Null) => {
+                    for key in response_keys {
+                        object.entry(Name::new(key)).or_insert(ConstValue::Null);
+                    }
+                    true
+                }
+                (target, value) => merge_data(target, value, policy),
+            }
         }
 
         #[inline]
@@ -185,6 +628,9 @@ impl<'e, T: Coordinator> Executor<'e, T> {
         fn flatten_values(
             target: &mut ConstValue,
             path: &[PathSegment<'_>],
+            response_keys: &[Cow<'_, str>],
+            policy: ListMergePolicy,
+            merged: &mut bool,
             n: &mut usize,
             values: &mut [ConstValue],
         ) {
@@ -197,17 +643,25 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             if is_last {
                 match target {
                     ConstValue::Object(object) if !segment.is_list => {
-                        if let Some(target) = object.get_mut(segment.name) {
-                            if let Some(value) = take_value(n, values) {
-                                merge_data(target, value);
+                        if let Some(target @ ConstValue::Object(_)) =
+                            object.get_mut(segment.name.as_ref())
+                        {
+                            if is_possible_value(target, segment.possible_type.as_deref()) {
+                                if let Some(value) = take_value(n, values) {
+                                    *merged &= merge_entity(target, value, response_keys, policy);
+                                }
                             }
                         }
                     }
                     ConstValue::Object(object) if segment.is_list => {
-                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name) {
-                            for element in array {
+                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name.as_ref())
+                        {
+                            for element in array.iter_mut().filter(|element| {
+                                matches!(element, ConstValue::Object(_))
+                                    && is_possible_value(element, segment.possible_type.as_deref())
+                            }) {
                                 if let Some(value) = take_value(n, values) {
-                                    merge_data(element, value);
+                                    *merged &= merge_entity(element, value, response_keys, policy);
                                 }
                             }
                         }
@@ -217,14 +671,35 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             } else {
                 match target {
                     ConstValue::Object(object) if !segment.is_list => {
-                        if let Some(next_value) = object.get_mut(segment.name) {
-                            flatten_values(next_value, &path[1..], n, values);
+                        if let Some(next_value) = object.get_mut(segment.name.as_ref()) {
+                            if is_possible_value(next_value, segment.possible_type.as_deref()) {
+                                flatten_values(
+                                    next_value,
+                                    &path[1..],
+                                    response_keys,
+                                    policy,
+                                    merged,
+                                    n,
+                                    values,
+                                );
+                            }
                         }
                     }
                     ConstValue::Object(object) if segment.is_list => {
-                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name) {
-                            for element in array {
-                                flatten_values(element, &path[1..], n, values);
+                        if let Some(ConstValue::List(array)) = object.get_mut(segment.name.as_ref())
+                        {
+                            for element in array.iter_mut().filter(|element| {
+                                is_possible_value(element, segment.possible_type.as_deref())
+                            }) {
+                                flatten_values(
+                                    element,
+                                    &path[1..],
+                                    response_keys,
+                                    policy,
+                                    merged,
+                                    n,
+                                    values,
+                                );
                             }
                         }
                     }
@@ -233,86 +708,2645 @@ impl<'e, T: Coordinator> Executor<'e, T> {
             }
         }
 
-        let representations = {
+        let (representations, entity_paths) = {
             let mut representations = Vec::new();
+            let mut entity_paths = Vec::new();
             let mut resp = self.resp.lock();
-            get_representations(
-                &mut representations,
-                &mut resp.data,
-                &flatten.path,
-                flatten.prefix,
-            );
-            let mut variables = Variables::default();
-            variables.insert(
-                Name::new("representations"),
-                ConstValue::List(representations),
-            );
-            variables
+            for (path, prefix) in flatten.paths() {
+                get_representations(
+                    &mut representations,
+                    &mut entity_paths,
+                    &mut Vec::new(),
+                    &mut resp.data,
+                    path,
+                    prefix,
+                );
+            }
+            (representations, entity_paths)
+        };
+        let (representations, valid_paths, skipped) = {
+            let mut valid_representations = Vec::new();
+            let mut valid_paths = Vec::new();
+            let mut skipped = Vec::new();
+            let mut resp = self.resp.lock();
+            for (idx, (representation, entity_path)) in
+                representations.into_iter().zip(&entity_paths).enumerate()
+            {
+                let missing = match &representation {
+                    ConstValue::Object(object) => flatten
+                        .required
+                        .iter()
+                        .find(|name| !object.contains_key(name.as_ref())),
+                    _ => None,
+                };
+                match missing {
+                    Some(name) => {
+                        resp.errors.push(ServerError {
+                            message: format!(
+                                "Entity '{}' is missing the required field '{}' for service '{}'.",
+                                flatten.parent_type, name, flatten.service
+                            ),
+                            locations: Default::default(),
+                            path: entity_path.clone(),
+                            extensions: Default::default(),
+                        });
+                        skipped.push(idx);
+                    }
+                    None => {
+                        valid_representations.push(representation);
+                        valid_paths.push(entity_path.clone());
+                    }
+                }
+            }
+            (valid_representations, valid_paths, skipped)
         };
+        Span::current().record(
+            "graphgate.representations.count",
+            &(representations.len() as u64),
+        );
+        if !self.reserve_representations(representations.len()) {
+            return;
+        }
 
-        let res = self
-            .coordinator
-            .query(flatten.service, &flatten.query, representations)
-            .await;
+        let service_options = self.options.service_options(&flatten.service);
+        let max_body_size = service_options.and_then(|options| options.max_body_size);
+        let strict_entities = service_options
+            .map(|options| options.strict_entities)
+            .unwrap_or_default();
+        let chunks = if representations.is_empty() && !skipped.is_empty() {
+            Vec::new()
+        } else {
+            chunk_representations(representations, &flatten.query, max_body_size)
+        };
+        let start = self.clock.now();
+        let results = futures_util::future::join_all(chunks.into_iter().map(|chunk| async move {
+            let len = chunk.len();
+            let mut variables = Variables::default();
+            variables.insert(Name::new("representations"), ConstValue::List(chunk));
+            if !self.reserve_request_bytes(&flatten.query, &variables) {
+                return (len, None);
+            }
+            let res = self
+                .query_service(&flatten.service, &flatten.query, variables, true)
+                .await
+                .map(|resp| self.intercept_response(&flatten.service, resp));
+            (len, Some(res))
+        }))
+        .await;
+        record_duration(self.clock.now().saturating_duration_since(start));
         let current_resp = &mut self.resp.lock();
 
-        match res {
-            Ok(resp) => {
-                if resp.errors.is_empty() {
+        let mut values = Vec::new();
+        for (len, res) in results {
+            let offset = values.len();
+            match res {
+                Some(Ok(resp)) => {
+                    let has_errors = !resp.errors.is_empty();
+                    if has_errors {
+                        let entity_paths = &valid_paths[offset..offset + len];
+                        let errors = resp
+                            .errors
+                            .into_iter()
+                            .map(|err| ServerError {
+                                path: entity_error_path(err.path, entity_paths),
+                                ..err
+                            })
+                            .collect();
+                        self.report_errors(&mut current_resp.errors, &flatten.service, errors);
+                    }
                     if let ConstValue::Object(mut data) = resp.data {
-                        if let Some(ConstValue::List(mut values)) = data.remove("_entities") {
-                            let mut n = 0;
-                            flatten_values(
-                                &mut current_resp.data,
-                                &flatten.path,
-                                &mut n,
-                                &mut values,
-                            );
+                        if let Some(ConstValue::List(mut entities)) = data.remove("_entities") {
+                            if strict_entities && !has_errors {
+                                current_resp.errors.extend(
+                                    entities
+                                        .iter()
+                                        .zip(&valid_paths[offset..])
+                                        .filter(|(entity, _)| matches!(entity, ConstValue::Null))
+                                        .map(|(_, entity_path)| ServerError {
+                                            message: format!(
+                                                "Entity '{}' was not found in service '{}'.",
+                                                flatten.parent_type, flatten.service
+                                            ),
+                                            locations: Default::default(),
+                                            path: entity_path.clone(),
+                                            extensions: Default::default(),
+                                        }),
+                                );
+                            }
+                            let mut too_deep = false;
+                            for entity in &mut entities {
+                                if exceeds_depth(entity, MAX_RESPONSE_DEPTH) {
+                                    *entity = ConstValue::Null;
+                                    too_deep = true;
+                                }
+                            }
+                            if too_deep {
+                                current_resp
+                                    .errors
+                                    .push(response_too_deep_error(&flatten.service));
+                            }
+                            values.extend(entities);
                         }
                     }
-                } else {
-                    merge_errors(&mut current_resp.errors, resp.errors);
                 }
+                Some(Err(err)) => self.report_errors(
+                    &mut current_resp.errors,
+                    &flatten.service,
+                    vec![ServerError {
+                        message: err.to_string(),
+                        locations: Default::default(),
+                        path: response_path_error_path(&flatten.path),
+                        extensions: Default::default(),
+                    }],
+                ),
+                None => {}
             }
-            Err(err) => {
-                current_resp.errors.push(ServerError {
-                    message: err.to_string(),
-                    locations: Default::default(),
-                });
+            values.resize(offset + len, ConstValue::Null);
+        }
+        for idx in skipped {
+            values.insert(idx, ConstValue::Null);
+        }
+
+        if self.options.expose_sources {
+            let paths = entity_paths
+                .iter()
+                .zip(&values)
+                .filter(|(_, value)| matches!(value, ConstValue::Object(_)))
+                .flat_map(|(entity_path, _)| {
+                    flatten
+                        .response_keys
+                        .iter()
+                        .map(move |key| format!("{}.{}", format_error_path(entity_path), key))
+                })
+                .collect::<Vec<_>>();
+            record_sources(current_resp, &flatten.service, paths);
+        }
+
+        let mut n = 0;
+        let mut merged = true;
+        for (path, _) in flatten.paths() {
+            flatten_values(
+                &mut current_resp.data,
+                path,
+                &flatten.response_keys,
+                self.options.list_merge_policy,
+                &mut merged,
+                &mut n,
+                &mut values,
+            );
+        }
+        if !merged {
+            current_resp
+                .errors
+                .push(list_length_mismatch_error(&flatten.service));
+        }
+    }
+
+    async fn query_service(
+        &self,
+        service: &str,
+        query: &str,
+        variables: Variables,
+        retryable: bool,
+    ) -> Result<Response, String> {
+        if !retryable {
+            return self
+                .query_service_with_retry(service, query, variables, false)
+                .await;
+        }
+
+        let key = request_key(service, query, &variables);
+        let leader = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get_mut(&key) {
+                Some((receiver, followers)) => {
+                    *followers += 1;
+                    Err(receiver.clone())
+                }
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    in_flight.insert(key, (receiver, 0));
+                    Ok(sender)
+                }
+            }
+        };
+
+        match leader {
+            Ok(sender) => {
+                let res = self
+                    .query_service_with_retry(service, query, variables, true)
+                    .await;
+                let followers = self
+                    .in_flight
+                    .lock()
+                    .remove(&key)
+                    .map(|(_, followers)| followers)
+                    .unwrap_or_default();
+                if followers > 0 {
+                    let _ = sender.send(Some(res.clone()));
+                }
+                res
             }
+            Err(mut receiver) => loop {
+                if let Some(res) = &*receiver.borrow() {
+                    return res.clone();
+                }
+                if receiver.changed().await.is_err() {
+                    return self
+                        .query_service_with_retry(service, query, variables, true)
+                        .await;
+                }
+            },
         }
     }
-}
 
-fn merge_data(target: &mut ConstValue, value: ConstValue) {
-    match (target, value) {
-        (target @ ConstValue::Null, fragment) => *target = fragment,
-        (ConstValue::Object(object), ConstValue::Object(fragment_object)) => {
-            for (key, value) in fragment_object {
-                match object.get_mut(&key) {
-                    Some(target) => merge_data(target, value),
+    async fn query_service_with_retry(
+        &self,
+        service: &str,
+        query: &str,
+        mut variables: Variables,
+        retryable: bool,
+    ) -> Result<Response, String> {
+        let retry = self.options.retry.as_ref().filter(|_| retryable);
+        let max_attempts = retry.map(|retry| retry.max_attempts.max(1)).unwrap_or(1);
+        let retry_budget = self.retry_budget.as_ref().filter(|_| retry.is_some());
+        if let Some(retry_budget) = retry_budget {
+            retry_budget.deposit(service);
+        }
+        let mut attempt = 1;
+        loop {
+            let attempt_variables = if attempt < max_attempts {
+                variables.clone()
+            } else {
+                std::mem::take(&mut variables)
+            };
+            match self
+                .query_service_once(service, query, attempt_variables)
+                .await
+            {
+                Err(err)
+                    if err.retryable
+                        && attempt < max_attempts
+                        && retry_budget.map_or(true, |budget| budget.withdraw(service)) =>
+                {
+                    let delay = retry.map(|retry| retry.delay(attempt)).unwrap_or_default();
+                    tracing::debug!(
+                        service,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err.message,
+                        "Retrying subgraph query"
+                    );
+                    self.clock.sleep(delay).await;
+                    attempt += 1;
+                }
+                res => return res.map_err(|err| err.message),
+            }
+        }
+    }
+
+    async fn query_service_once(
+        &self,
+        service: &str,
+        query: &str,
+        variables: Variables,
+    ) -> Result<Response, QueryError> {
+        let fut = self
+            .coordinator
+            .query(service, query, variables, &self.headers);
+        let res = match self.options.service_timeout(service) {
+            Some(timeout) => {
+                match futures_util::future::select(fut, self.clock.sleep(timeout)).await {
+                    Either::Left((res, _)) => res,
+                    Either::Right(_) => {
+                        tracing::debug!(
+                            service,
+                            timeout_ms = timeout.as_millis() as u64,
+                            "Subgraph query timed out"
+                        );
+                        return Err(QueryError {
+                            message: format!(
+                                "Subgraph '{}' timed out after {} ms.",
+                                service,
+                                timeout.as_millis()
+                            ),
+                            retryable: false,
+                        });
+                    }
+                }
+            }
+            None => fut.await,
+        };
+        res.map_err(|err| QueryError {
+            message: err.to_string(),
+            retryable: self.coordinator.is_retryable(&err),
+        })
+    }
+
+    fn intercept_response(&self, service: &str, mut resp: Response) -> Response {
+        for interceptor in &self.interceptors {
+            interceptor.on_subgraph_response(service, &mut resp);
+        }
+        resp
+    }
+
+    fn reserve_request_bytes(&self, query: &str, variables: &Variables) -> bool {
+        let max_request_bytes = match self.options.max_request_bytes {
+            Some(max_request_bytes) => max_request_bytes,
+            None => return true,
+        };
+        let size = query.len()
+            + REQUEST_BODY_OVERHEAD
+            + variables
+                .iter()
+                .map(|(name, value)| name.len() + value.to_string().len())
+                .sum::<usize>();
+        let total = self.request_bytes.fetch_add(size, Ordering::SeqCst) + size;
+        if total <= max_request_bytes {
+            return true;
+        }
+        if !self.request_bytes_exceeded.swap(true, Ordering::SeqCst) {
+            self.resp.lock().errors.push(ServerError {
+                message: format!(
+                    "Subgraph requests exceeded the limit of {} bytes per operation.",
+                    max_request_bytes
+                ),
+                locations: Default::default(),
+                path: Default::default(),
+                extensions: Default::default(),
+            });
+        }
+        false
+    }
+
+    fn reserve_representations(&self, count: usize) -> bool {
+        let max_representations = match self.options.max_representations {
+            Some(max_representations) => max_representations,
+            None => return true,
+        };
+        let total = self.representations.fetch_add(count, Ordering::SeqCst) + count;
+        if total <= max_representations {
+            return true;
+        }
+        if !self.representations_exceeded.swap(true, Ordering::SeqCst) {
+            self.resp.lock().errors.push(ServerError {
+                message: format!(
+                    "Operation requires at least {} entity representations, which exceeds the limit of {}.",
+                    total, max_representations
+                ),
+                locations: Default::default(),
+                path: Default::default(),
+                extensions: Default::default(),
+            });
+        }
+        false
+    }
+
+    fn report_errors(
+        &self,
+        target: &mut Vec<ServerError>,
+        service: &str,
+        errors: Vec<ServerError>,
+    ) {
+        let optional = self
+            .options
+            .service_options(service)
+            .map(|options| options.optional)
+            .unwrap_or_default();
+        if optional {
+            for err in errors {
+                tracing::warn!(service, error = %err.message, "Optional service failed");
+            }
+        } else {
+            merge_errors(target, service, errors);
+        }
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn execute_compute_node(&self, compute: &ComputeNode<'_>) {
+        fn compute_values(
+            target: &mut ConstValue,
+            path: &[PathSegment<'_>],
+            field: &ComputeField<'_>,
+        ) {
+            match target {
+                ConstValue::List(array) => {
+                    for element in array {
+                        compute_values(element, path, field);
+                    }
+                }
+                ConstValue::Object(object) => match path.split_first() {
+                    Some((segment, path)) => {
+                        if let Some(next_value) = object.get_mut(segment.name.as_ref()) {
+                            compute_values(next_value, path, field);
+                        }
+                    }
                     None => {
-                        object.insert(key, value);
+                        if let Some(ConstValue::String(typename)) = object.get("__typename") {
+                            if *typename != field.parent_type {
+                                return;
+                            }
+                        }
+                        let value = match &field.resolver {
+                            Some(resolver) => resolver.resolve(object),
+                            None => ConstValue::Null,
+                        };
+                        object.insert(Name::new(&field.response_key), value);
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let mut current_resp = self.resp.lock();
+        if current_resp.data == ConstValue::Null && current_resp.errors.is_empty() {
+            current_resp.data = ConstValue::Object(Default::default());
+        }
+        for field in &compute.fields {
+            compute_values(&mut current_resp.data, &field.path, field);
+        }
+    }
+
+    fn execute_null_check_node(&self, null_check: &NullCheckNode<'_>) {
+        fn check_object(
+            schema: &ComposedSchema,
+            object: &mut BTreeMap<Name, ConstValue>,
+            fields: &[ResponseField<'_>],
+            path: &mut Vec<ErrorPath>,
+            errors: &mut Vec<ServerError>,
+        ) -> bool {
+            let typename = match object.get("__typename") {
+                Some(ConstValue::String(typename)) => Some(typename.clone()),
+                _ => None,
+            };
+            for field in fields {
+                if let Some(typename) = &typename {
+                    let is_possible_type = schema
+                        .types
+                        .get(field.parent_type.as_ref())
+                        .map(|ty| ty.is_possible_type(typename))
+                        .unwrap_or_default();
+                    if !is_possible_type {
+                        continue;
+                    }
+                }
+                if let Some(value) = object.get_mut(field.response_key.as_ref()) {
+                    path.push(ErrorPath::Name(field.response_key.to_string()));
+                    let is_valid = check_value(schema, value, field, &field.ty, path, errors);
+                    path.pop();
+                    if !is_valid {
+                        return false;
                     }
                 }
             }
+            true
         }
-        (ConstValue::List(array), ConstValue::List(fragment_array))
-            if array.len() == fragment_array.len() =>
-        {
-            for (idx, element) in fragment_array.into_iter().enumerate() {
-                merge_data(&mut array[idx], element);
+
+        fn check_value(
+            schema: &ComposedSchema,
+            value: &mut ConstValue,
+            field: &ResponseField<'_>,
+            ty: &Type,
+            path: &mut Vec<ErrorPath>,
+            errors: &mut Vec<ServerError>,
+        ) -> bool {
+            let is_valid = match (&mut *value, &ty.base) {
+                (ConstValue::Null, _) => {
+                    if !ty.nullable && !errors.iter().any(|err| overlaps(&err.path, path)) {
+                        errors.push(ServerError {
+                            message: format!(
+                                "Cannot return null for non-nullable field {}.{}.",
+                                field.parent_type, field.field_name
+                            ),
+                            locations: Default::default(),
+                            path: path.clone(),
+                            extensions: Default::default(),
+                        });
+                    }
+                    return ty.nullable;
+                }
+                (ConstValue::List(items), BaseType::List(item_ty)) => {
+                    items.iter_mut().enumerate().all(|(idx, item)| {
+                        path.push(ErrorPath::Index(idx));
+                        let is_valid = check_value(schema, item, field, item_ty, path, errors);
+                        path.pop();
+                        is_valid
+                    })
+                }
+                (ConstValue::Object(object), BaseType::Named(_)) => {
+                    check_object(schema, object, &field.selection_set, path, errors)
+                }
+                _ => true,
+            };
+            if !is_valid {
+                *value = ConstValue::Null;
+                return ty.nullable;
             }
+            true
+        }
+
+        fn overlaps(a: &[ErrorPath], b: &[ErrorPath]) -> bool {
+            !a.is_empty() && a.iter().zip(b).all(|(a, b)| a == b)
+        }
+
+        let mut current_resp = self.resp.lock();
+        let current_resp = &mut *current_resp;
+        if let ConstValue::Object(object) = &mut current_resp.data {
+            let mut errors = std::mem::take(&mut current_resp.errors);
+            if !check_object(
+                self.schema,
+                object,
+                &null_check.fields,
+                &mut Vec::new(),
+                &mut errors,
+            ) {
+                current_resp.data = ConstValue::Null;
+            }
+            current_resp.errors = errors;
         }
-        _ => {}
     }
 }
 
-fn merge_errors(target: &mut Vec<ServerError>, errors: Vec<ServerError>) {
-    for err in errors {
-        target.push(ServerError {
-            message: err.message,
-            locations: Default::default(),
-        })
+fn chunk_representations(
+    representations: Vec<ConstValue>,
+    query: &str,
+    max_body_size: Option<usize>,
+) -> Vec<Vec<ConstValue>> {
+    let max_body_size = match max_body_size {
+        Some(max_body_size) => max_body_size,
+        None => return vec![representations],
+    };
+    let base_size = query.len() + REQUEST_BODY_OVERHEAD;
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    let mut chunk_size = base_size;
+
+    for representation in representations {
+        let size = representation.to_string().len() + 1;
+        if !chunk.is_empty() && chunk_size + size > max_body_size {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_size = base_size;
+        }
+        chunk_size += size;
+        chunk.push(representation);
+    }
+    chunks.push(chunk);
+    chunks
+}
+
+fn is_possible_type(object: &BTreeMap<Name, ConstValue>, possible_type: Option<&str>) -> bool {
+    match (object.get("__typename"), possible_type) {
+        (Some(ConstValue::String(typename)), Some(possible_type)) => typename == possible_type,
+        _ => true,
+    }
+}
+
+fn is_possible_value(value: &ConstValue, possible_type: Option<&str>) -> bool {
+    match value {
+        ConstValue::Object(object) => is_possible_type(object, possible_type),
+        _ => true,
+    }
+}
+
+fn exceeds_depth(value: &ConstValue, max_depth: usize) -> bool {
+    let mut stack = vec![(value, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        if depth > max_depth {
+            return true;
+        }
+        match value {
+            ConstValue::Object(object) => {
+                stack.extend(object.values().map(|value| (value, depth + 1)))
+            }
+            ConstValue::List(array) => stack.extend(array.iter().map(|value| (value, depth + 1))),
+            _ => {}
+        }
+    }
+    false
+}
+
+fn introspection_depth(selection_set: &IntrospectionSelectionSet) -> usize {
+    selection_set
+        .0
+        .iter()
+        .filter(|field| !field.selection_set.0.is_empty())
+        .map(|field| 1 + introspection_depth(&field.selection_set))
+        .max()
+        .unwrap_or_default()
+}
+
+fn response_path_error_path(path: &ResponsePath<'_>) -> Vec<ErrorPath> {
+    path.iter()
+        .map(|segment| ErrorPath::Name(segment.name.to_string()))
+        .collect()
+}
+
+fn fetch_error_path(fetch: &FetchNode<'_>) -> Vec<ErrorPath> {
+    match fetch.response_keys.as_slice() {
+        [response_key] => vec![ErrorPath::Name(response_key.to_string())],
+        _ => Vec::new(),
+    }
+}
+
+fn response_too_deep_error(service: &str) -> ServerError {
+    ServerError {
+        message: format!(
+            "Response from service '{}' exceeds the maximum depth of {}.",
+            service, MAX_RESPONSE_DEPTH
+        ),
+        locations: Default::default(),
+        path: Default::default(),
+        extensions: Default::default(),
+    }
+}
+
+fn merge_data(target: &mut ConstValue, value: ConstValue, policy: ListMergePolicy) -> bool {
+    match (target, value) {
+        (target @ ConstValue::Null, fragment) => {
+            *target = fragment;
+            true
+        }
+        (ConstValue::Object(object), ConstValue::Object(fragment_object)) => {
+            let mut merged = true;
+            for (key, value) in fragment_object {
+                match object.get_mut(&key) {
+                    Some(target) => merged &= merge_data(target, value, policy),
+                    None => {
+                        object.insert(key, value);
+                    }
+                }
+            }
+            merged
+        }
+        (ConstValue::List(array), ConstValue::List(mut fragment_array)) => {
+            if array.len() != fragment_array.len() {
+                match policy {
+                    ListMergePolicy::Truncate => {
+                        let len = array.len().min(fragment_array.len());
+                        array.truncate(len);
+                        fragment_array.truncate(len);
+                    }
+                    ListMergePolicy::Pad => {
+                        let len = array.len().max(fragment_array.len());
+                        array.resize(len, ConstValue::Null);
+                        fragment_array.resize(len, ConstValue::Null);
+                    }
+                    ListMergePolicy::Replace => {
+                        *array = fragment_array;
+                        return true;
+                    }
+                    ListMergePolicy::Error => return false,
+                }
+            }
+            let mut merged = true;
+            for (idx, element) in fragment_array.into_iter().enumerate() {
+                merged &= merge_data(&mut array[idx], element, policy);
+            }
+            merged
+        }
+        _ => true,
+    }
+}
+
+fn list_length_mismatch_error(service: &str) -> ServerError {
+    ServerError {
+        message: format!(
+            "Service '{}' returned a list whose length does not match the existing data.",
+            service
+        ),
+        locations: Default::default(),
+        path: Default::default(),
+        extensions: Default::default(),
+    }
+}
+
+fn record_duration(duration: Duration) {
+    Span::current().record("graphgate.duration_ms", &(duration.as_millis() as u64));
+}
+
+fn remove_key_fields(value: &mut ConstValue) {
+    match value {
+        ConstValue::Object(object) => {
+            object.retain(|name, _| !name.starts_with(KEY_PREFIX));
+            object.values_mut().for_each(remove_key_fields);
+        }
+        ConstValue::List(array) => array.iter_mut().for_each(remove_key_fields),
+        _ => {}
+    }
+}
+
+fn split_aliases(data: ConstValue, aliases: &[AliasedField<'_>]) -> ConstValue {
+    fn project(value: &ConstValue, projection: &Projection<'_>) -> ConstValue {
+        match value {
+            ConstValue::Object(object) => {
+                let mut res = BTreeMap::new();
+                for (name, value) in object {
+                    match projection
+                        .fields
+                        .iter()
+                        .find(|(key, _)| key == name.as_str())
+                    {
+                        Some((_, Some(projection))) => {
+                            res.insert(name.clone(), project(value, projection));
+                        }
+                        Some((_, None)) => {
+                            res.insert(name.clone(), value.clone());
+                        }
+                        None => {
+                            if projection
+                                .key_prefixes
+                                .iter()
+                                .any(|prefix| name.starts_with(&key_prefix(*prefix)))
+                            {
+                                res.insert(name.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+                ConstValue::Object(res)
+            }
+            ConstValue::List(items) => {
+                ConstValue::List(items.iter().map(|item| project(item, projection)).collect())
+            }
+            value => value.clone(),
+        }
+    }
+
+    let mut object = match data {
+        ConstValue::Object(object) if !aliases.is_empty() => object,
+        data => return data,
+    };
+    let mut sources = BTreeMap::new();
+    for aliased in aliases {
+        if let Some(value) = object.remove(aliased.source.as_ref()) {
+            sources.insert(aliased.source.as_ref(), value);
+        }
+    }
+    for aliased in aliases {
+        if let Some(value) = sources.get(aliased.source.as_ref()) {
+            let value = match &aliased.projection {
+                Some(projection) => project(value, projection),
+                None => value.clone(),
+            };
+            object.insert(Name::new(&aliased.response_key), value);
+        }
+    }
+    ConstValue::Object(object)
+}
+
+fn request_key(service: &str, query: &str, variables: &Variables) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    service.hash(&mut hasher);
+    query.hash(&mut hasher);
+    for (name, value) in variables.iter() {
+        name.as_str().hash(&mut hasher);
+        value.to_string().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn merge_errors(target: &mut Vec<ServerError>, service: &str, errors: Vec<ServerError>) {
+    for err in errors {
+        let mut extensions = err.extensions;
+        extensions.insert(
+            "serviceName".to_string(),
+            ConstValue::String(service.to_string()),
+        );
+        target.push(ServerError {
+            message: err.message,
+            path: err.path,
+            locations: Default::default(),
+            extensions,
+        })
+    }
+}
+
+fn format_error_path(path: &[ErrorPath]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            ErrorPath::Name(name) => name.clone(),
+            ErrorPath::Index(idx) => idx.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn record_sources(response: &mut Response, service: &str, paths: Vec<String>) {
+    let sources = response
+        .extensions
+        .entry("sources".to_string())
+        .or_insert_with(|| ConstValue::Object(Default::default()));
+    if let ConstValue::Object(sources) = sources {
+        for path in paths {
+            sources.insert(Name::new(path), ConstValue::String(service.to_string()));
+        }
+    }
+}
+
+fn entity_error_path(path: Vec<ErrorPath>, entity_paths: &[Vec<ErrorPath>]) -> Vec<ErrorPath> {
+    let index = match path.as_slice() {
+        [ErrorPath::Name(name), ErrorPath::Index(index), ..] if name == "_entities" => *index,
+        _ => return Vec::new(),
+    };
+    match entity_paths.get(index) {
+        Some(entity_path) => entity_path
+            .iter()
+            .cloned()
+            .chain(path.into_iter().skip(2))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::FutureExt;
+    use parser::{parse_query, parse_schema};
+    use value::value;
+
+    use super::*;
+    use crate::PlanBuilder;
+
+    struct MockCoordinator(Vec<(&'static str, &'static str, ConstValue)>);
+
+    #[async_trait::async_trait]
+    impl Coordinator for MockCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            service: &str,
+            query: &str,
+            _variables: Variables,
+            _headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            let data = self
+                .0
+                .iter()
+                .find(|(name, pattern, _)| *name == service && query.contains(pattern))
+                .map(|(_, _, data)| data.clone())
+                .unwrap_or_default();
+            Ok(Response {
+                data,
+                errors: Vec::new(),
+                extensions: Default::default(),
+            })
+        }
+    }
+
+    fn combine(services: &[(&str, &str)]) -> ComposedSchema {
+        ComposedSchema::combine(
+            services
+                .iter()
+                .map(|(service, sdl)| (service.to_string(), parse_schema(sdl).unwrap())),
+        )
+        .unwrap()
+    }
+
+    fn users_reviews_schema() -> ComposedSchema {
+        users_reviews_schema_with("type Query { users: [User!]! }", &[])
+    }
+
+    fn users_reviews_schema_with(query: &str, services: &[(&str, &str)]) -> ComposedSchema {
+        let accounts = format!(
+            r#"{}
+            type User @key(fields: "id") {{ id: ID! username: String! }}"#,
+            query
+        );
+        let mut sdls = vec![
+            ("accounts", accounts.as_str()),
+            (
+                "reviews",
+                r#"extend type User @key(fields: "id") { id: ID! @external reviews: [String!] }"#,
+            ),
+        ];
+        sdls.extend_from_slice(services);
+        combine(&sdls)
+    }
+
+    fn execute(
+        schema: &ComposedSchema,
+        query: &str,
+        coordinator: MockCoordinator,
+        options: ExecutorOptions,
+    ) -> Response {
+        let document = parse_query(query).unwrap();
+        let plan = PlanBuilder::new(schema, document).plan().unwrap();
+        Executor::new(schema, coordinator)
+            .options(options)
+            .execute(&plan)
+            .now_or_never()
+            .unwrap()
+    }
+
+    fn execute_partial_entities(options: ExecutorOptions) -> Response {
+        let schema = users_reviews_schema();
+        let coordinator = MockCoordinator(vec![
+            (
+                "accounts",
+                "",
+                value!({
+                    "users": [
+                        { "username": "a", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" },
+                        { "username": "b", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "2" }
+                    ]
+                }),
+            ),
+            (
+                "reviews",
+                "",
+                value!({ "_entities": [{ "reviews": ["good"] }, null] }),
+            ),
+        ]);
+        execute(
+            &schema,
+            "{ users { username reviews } }",
+            coordinator,
+            options,
+        )
+    }
+
+    #[test]
+    fn entity_not_found() {
+        let resp = execute_partial_entities(ExecutorOptions::default());
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "users": [
+                    { "username": "a", "reviews": ["good"] },
+                    { "username": "b", "reviews": null }
+                ]
+            })
+        );
+
+        let resp = execute_partial_entities(ExecutorOptions::default().service(
+            "reviews",
+            ServiceOptions {
+                strict_entities: true,
+                ..Default::default()
+            },
+        ));
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Entity 'User' was not found in service 'reviews'."
+        );
+        assert_eq!(
+            resp.errors[0].path,
+            vec![ErrorPath::Name("users".to_string()), ErrorPath::Index(1)]
+        );
+        assert_eq!(
+            resp.errors[0].field_names().collect::<Vec<_>>(),
+            vec!["users"]
+        );
+    }
+
+    #[test]
+    fn expose_sources() {
+        let resp = execute_partial_entities(ExecutorOptions {
+            expose_sources: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            resp.extensions["sources"],
+            value!({ "users": "accounts", "users.0.reviews": "reviews" })
+        );
+    }
+
+    #[test]
+    fn entities_under_null_parent() {
+        let schema = users_reviews_schema_with(
+            "type Query { posts: [Post!]! } type Post { author: User }",
+            &[],
+        );
+        let coordinator = MockCoordinator(vec![
+            (
+                "accounts",
+                "",
+                value!({
+                    "posts": [
+                        { "author": { "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" } },
+                        { "author": null },
+                        { "author": { "__graphgate_key1___typename": "User", "__graphgate_key1_id": "3" } }
+                    ]
+                }),
+            ),
+            (
+                "reviews",
+                "",
+                value!({ "_entities": [{ "reviews": ["a"] }, { "reviews": ["c"] }] }),
+            ),
+        ]);
+        let resp = execute(
+            &schema,
+            "{ posts { author { reviews } } }",
+            coordinator,
+            ExecutorOptions::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "posts": [
+                    { "author": { "reviews": ["a"] } },
+                    { "author": null },
+                    { "author": { "reviews": ["c"] } }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn non_null_bubbling() {
+        let schema = combine(&[(
+            "accounts",
+            r#"type Query { users: [User!] me: User! }
+            type User { id: ID! name: String! }"#,
+        )]);
+        let query = "{ users { id name } me { id name } }";
+
+        let coordinator = MockCoordinator(vec![(
+            "accounts",
+            "",
+            value!({
+                "users": [{ "id": "1", "name": "a" }, { "id": "2", "name": null }],
+                "me": { "id": "1", "name": "a" }
+            }),
+        )]);
+        let resp = execute(&schema, query, coordinator, ExecutorOptions::default());
+        assert_eq!(
+            resp.data,
+            value!({ "users": null, "me": { "id": "1", "name": "a" } })
+        );
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Cannot return null for non-nullable field User.name."
+        );
+        assert_eq!(
+            resp.errors[0].path,
+            vec![
+                ErrorPath::Name("users".to_string()),
+                ErrorPath::Index(1),
+                ErrorPath::Name("name".to_string())
+            ]
+        );
+
+        let coordinator = MockCoordinator(vec![(
+            "accounts",
+            "",
+            value!({ "users": [], "me": { "id": null, "name": "a" } }),
+        )]);
+        let resp = execute(&schema, query, coordinator, ExecutorOptions::default());
+        assert_eq!(resp.data, ConstValue::Null);
+        assert_eq!(
+            resp.errors[0].path,
+            vec![
+                ErrorPath::Name("me".to_string()),
+                ErrorPath::Name("id".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn entities_with_different_concrete_types() {
+        let schema = combine(&[
+            (
+                "accounts",
+                r#"type Query { media: [Media!]! }
+                interface Media { id: ID! }
+                type Book implements Media @key(fields: "id") { id: ID! }
+                type Movie implements Media @key(fields: "id") { id: ID! }"#,
+            ),
+            (
+                "reviews",
+                r#"extend type Book @key(fields: "id") { id: ID! @external pages: Int! }
+                extend type Movie @key(fields: "id") { id: ID! @external minutes: Int! }"#,
+            ),
+        ]);
+        let coordinator = MockCoordinator(vec![
+            (
+                "accounts",
+                "",
+                value!({
+                    "media": [
+                        { "__typename": "Book", "__graphgate_key1___typename": "Book", "__graphgate_key1_id": "1" },
+                        { "__typename": "Movie", "__graphgate_key2___typename": "Movie", "__graphgate_key2_id": "2" },
+                        { "__typename": "Book", "__graphgate_key1___typename": "Book", "__graphgate_key1_id": "3" }
+                    ]
+                }),
+            ),
+            (
+                "reviews",
+                "... on Book",
+                value!({ "_entities": [{ "pages": 100 }, { "pages": 300 }] }),
+            ),
+            (
+                "reviews",
+                "... on Movie",
+                value!({ "_entities": [{ "minutes": 90 }] }),
+            ),
+        ]);
+        let resp = execute(
+            &schema,
+            "{ media { __typename ... on Book { pages } ... on Movie { minutes } } }",
+            coordinator,
+            ExecutorOptions::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "media": [
+                    { "__typename": "Book", "pages": 100 },
+                    { "__typename": "Movie", "minutes": 90 },
+                    { "__typename": "Book", "pages": 300 }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn typename_in_flattened_entities() {
+        let schema = users_reviews_schema_with(
+            "type Query { users: [User!]! }",
+            &[(
+                "ratings",
+                r#"type Rating { score: Int! }
+                extend type User @key(fields: "id") { id: ID! @external ratings: [Rating!]! }"#,
+            )],
+        );
+        let coordinator = MockCoordinator(vec![
+            (
+                "accounts",
+                "",
+                value!({
+                    "users": [
+                        { "__typename": "User", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" },
+                        { "__typename": "User", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "2" }
+                    ]
+                }),
+            ),
+            (
+                "ratings",
+                "__typename",
+                value!({
+                    "_entities": [
+                        { "ratings": [{ "__typename": "Rating", "score": 5 }] },
+                        { "ratings": [] }
+                    ]
+                }),
+            ),
+        ]);
+        let resp = execute(
+            &schema,
+            "{ users { __typename ratings { __typename score } } }",
+            coordinator,
+            ExecutorOptions::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "users": [
+                    { "__typename": "User", "ratings": [{ "__typename": "Rating", "score": 5 }] },
+                    { "__typename": "User", "ratings": [] }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn field_resembling_key_prefix() {
+        let schema = users_reviews_schema();
+        let coordinator = MockCoordinator(vec![
+            (
+                "accounts",
+                "",
+                value!({
+                    "users": [{
+                        "__key1_name": "a",
+                        "__graphgate_key1___typename": "User",
+                        "__graphgate_key1_id": "1"
+                    }]
+                }),
+            ),
+            (
+                "reviews",
+                "",
+                value!({ "_entities": [{ "reviews": ["good"] }] }),
+            ),
+        ]);
+        let resp = execute(
+            &schema,
+            "{ users { __key1_name: username reviews } }",
+            coordinator,
+            ExecutorOptions::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({ "users": [{ "__key1_name": "a", "reviews": ["good"] }] })
+        );
+    }
+
+    #[test]
+    fn merge_abstract_fragments() {
+        let mut target = value!({
+            "search": [
+                { "__typename": "Book", "title": "Dune" },
+                { "__typename": "Movie", "title": "Alien" },
+            ]
+        });
+        let fragment = value!({
+            "search": [
+                { "__typename": "Book", "pages": 412 },
+                { "duration": 117 },
+            ]
+        });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Error));
+        assert_eq!(
+            target,
+            value!({
+                "search": [
+                    { "__typename": "Book", "title": "Dune", "pages": 412 },
+                    { "__typename": "Movie", "title": "Alien", "duration": 117 },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn list_merge_truncate() {
+        let mut target = value!({ "a": [{ "x": 1 }, { "x": 2 }, { "x": 3 }] });
+        let fragment = value!({ "a": [{ "y": 1 }, { "y": 2 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Truncate));
+        assert_eq!(
+            target,
+            value!({ "a": [{ "x": 1, "y": 1 }, { "x": 2, "y": 2 }] })
+        );
+    }
+
+    #[test]
+    fn list_merge_pad() {
+        let mut target = value!({ "a": [{ "x": 1 }] });
+        let fragment = value!({ "a": [{ "y": 1 }, { "y": 2 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Pad));
+        assert_eq!(target, value!({ "a": [{ "x": 1, "y": 1 }, { "y": 2 }] }));
+
+        let mut target = value!({ "a": [{ "x": 1 }, { "x": 2 }] });
+        let fragment = value!({ "a": [{ "y": 1 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Pad));
+        assert_eq!(target, value!({ "a": [{ "x": 1, "y": 1 }, { "x": 2 }] }));
+    }
+
+    #[test]
+    fn list_merge_replace() {
+        let mut target = value!({ "a": [{ "x": 1 }, { "x": 2 }] });
+        let fragment = value!({ "a": [{ "y": 1 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Replace));
+        assert_eq!(target, value!({ "a": [{ "y": 1 }] }));
+
+        let mut target = value!({ "a": [{ "x": 1 }] });
+        let fragment = value!({ "a": [{ "y": 1 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Replace));
+        assert_eq!(target, value!({ "a": [{ "x": 1, "y": 1 }] }));
+    }
+
+    #[test]
+    fn list_merge_null_element() {
+        let mut target = value!({ "a": [null, { "x": 2 }] });
+        let fragment = value!({ "a": [{ "y": 1 }, { "y": 2 }] });
+        assert!(merge_data(&mut target, fragment, ListMergePolicy::Error));
+        assert_eq!(target, value!({ "a": [{ "y": 1 }, { "x": 2, "y": 2 }] }));
+    }
+
+    #[test]
+    fn list_merge_error() {
+        let mut target = value!({ "a": [{ "x": 1 }, { "x": 2 }], "b": 1 });
+        let fragment = value!({ "a": [{ "y": 1 }], "c": 2 });
+        assert!(!merge_data(&mut target, fragment, ListMergePolicy::Error));
+        assert_eq!(
+            target,
+            value!({ "a": [{ "x": 1 }, { "x": 2 }], "b": 1, "c": 2 })
+        );
+    }
+
+    #[test]
+    fn entity_error_paths() {
+        let entity_paths = vec![
+            vec![ErrorPath::Name("users".to_string()), ErrorPath::Index(0)],
+            vec![ErrorPath::Name("users".to_string()), ErrorPath::Index(2)],
+        ];
+        assert_eq!(
+            entity_error_path(
+                vec![
+                    ErrorPath::Name("_entities".to_string()),
+                    ErrorPath::Index(1),
+                    ErrorPath::Name("reviews".to_string()),
+                ],
+                &entity_paths,
+            ),
+            vec![
+                ErrorPath::Name("users".to_string()),
+                ErrorPath::Index(2),
+                ErrorPath::Name("reviews".to_string()),
+            ]
+        );
+        assert!(
+            entity_error_path(vec![ErrorPath::Name("me".to_string())], &entity_paths).is_empty()
+        );
+    }
+
+    struct ErrorCoordinator {
+        inner: MockCoordinator,
+        service: &'static str,
+        errors: Vec<ServerError>,
+    }
+
+    #[async_trait::async_trait]
+    impl Coordinator for ErrorCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            service: &str,
+            query: &str,
+            variables: Variables,
+            headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            let mut resp = self.inner.query(service, query, variables, headers).await?;
+            if service == self.service {
+                resp.errors = self.errors.clone();
+            }
+            Ok(resp)
+        }
+    }
+
+    #[test]
+    fn entity_errors_in_aliased_list() {
+        let schema = users_reviews_schema();
+        let coordinator = ErrorCoordinator {
+            inner: MockCoordinator(vec![(
+                "accounts",
+                "",
+                value!({
+                    "people": [
+                        { "username": "a", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" },
+                        { "username": "b", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "2" },
+                        { "username": "c", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "3" }
+                    ]
+                }),
+            )]),
+            service: "reviews",
+            errors: vec![ServerError {
+                message: "Reviews unavailable.".to_string(),
+                locations: Default::default(),
+                path: vec![
+                    ErrorPath::Name("_entities".to_string()),
+                    ErrorPath::Index(2),
+                    ErrorPath::Name("comments".to_string()),
+                ],
+                extensions: Default::default(),
+            }],
+        };
+        let document = parse_query("{ people: users { username comments: reviews } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator)
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Reviews unavailable.");
+        assert_eq!(
+            resp.errors[0].path,
+            vec![
+                ErrorPath::Name("people".to_string()),
+                ErrorPath::Index(2),
+                ErrorPath::Name("comments".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_data_with_errors() {
+        let schema = combine(&[("accounts", "type Query { me: String version: String }")]);
+        let coordinator = ErrorCoordinator {
+            inner: MockCoordinator(vec![(
+                "accounts",
+                "",
+                value!({ "me": "a", "version": null }),
+            )]),
+            service: "accounts",
+            errors: vec![ServerError {
+                message: "Version unavailable.".to_string(),
+                locations: Default::default(),
+                path: vec![ErrorPath::Name("version".to_string())],
+                extensions: Default::default(),
+            }],
+        };
+        let document = parse_query("{ me version }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator)
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(resp.data, value!({ "me": "a", "version": null }));
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Version unavailable.");
+        assert_eq!(
+            resp.errors[0].path,
+            vec![ErrorPath::Name("version".to_string())]
+        );
+    }
+
+    #[test]
+    fn partial_entities_with_errors() {
+        let schema = users_reviews_schema();
+        let coordinator = ErrorCoordinator {
+            inner: MockCoordinator(vec![
+                (
+                    "accounts",
+                    "",
+                    value!({
+                        "users": [
+                            { "username": "a", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" },
+                            { "username": "b", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "2" }
+                        ]
+                    }),
+                ),
+                (
+                    "reviews",
+                    "",
+                    value!({ "_entities": [{ "reviews": ["good"] }, { "reviews": null }] }),
+                ),
+            ]),
+            service: "reviews",
+            errors: vec![ServerError {
+                message: "Reviews unavailable.".to_string(),
+                locations: Default::default(),
+                path: vec![
+                    ErrorPath::Name("_entities".to_string()),
+                    ErrorPath::Index(1),
+                    ErrorPath::Name("reviews".to_string()),
+                ],
+                extensions: Default::default(),
+            }],
+        };
+        let document = parse_query("{ users { username reviews } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator)
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(
+            resp.data,
+            value!({
+                "users": [
+                    { "username": "a", "reviews": ["good"] },
+                    { "username": "b", "reviews": null }
+                ]
+            })
+        );
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Reviews unavailable.");
+        assert_eq!(
+            resp.errors[0].path,
+            vec![
+                ErrorPath::Name("users".to_string()),
+                ErrorPath::Index(1),
+                ErrorPath::Name("reviews".to_string()),
+            ]
+        );
+    }
+
+    struct PendingCoordinator;
+
+    #[async_trait::async_trait]
+    impl Coordinator for PendingCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            _service: &str,
+            _query: &str,
+            _variables: Variables,
+            _headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            futures_util::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_timeout() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let document = parse_query("{ me }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, PendingCoordinator)
+            .options(ExecutorOptions {
+                timeout: Some(std::time::Duration::from_millis(10)),
+                ..Default::default()
+            })
+            .execute(&plan)
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Subgraph 'accounts' timed out after 10 ms."
+        );
+    }
+
+    #[test]
+    fn fetch_timeout_mock_clock() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let document = parse_query("{ me }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let clock = MockClock::default();
+        let mut fut = Executor::new(&schema, PendingCoordinator)
+            .options(ExecutorOptions {
+                timeout: Some(std::time::Duration::from_secs(10)),
+                ..Default::default()
+            })
+            .clock(clock.clone())
+            .execute(&plan)
+            .boxed_local();
+
+        assert!((&mut fut).now_or_never().is_none());
+        clock.advance(std::time::Duration::from_secs(5));
+        assert!((&mut fut).now_or_never().is_none());
+        clock.advance(std::time::Duration::from_secs(5));
+        let resp = fut.now_or_never().unwrap();
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Subgraph 'accounts' timed out after 10000 ms."
+        );
+    }
+
+    struct StalledCoordinator {
+        inner: MockCoordinator,
+        stalled: &'static str,
+    }
+
+    #[async_trait::async_trait]
+    impl Coordinator for StalledCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            service: &str,
+            query: &str,
+            variables: Variables,
+            headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            if service == self.stalled {
+                futures_util::future::pending::<()>().await;
+            }
+            self.inner.query(service, query, variables, headers).await
+        }
+    }
+
+    #[test]
+    fn total_timeout() {
+        let schema = users_reviews_schema();
+        let coordinator = StalledCoordinator {
+            inner: MockCoordinator(vec![(
+                "accounts",
+                "",
+                value!({
+                    "users": [
+                        { "username": "a", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" }
+                    ]
+                }),
+            )]),
+            stalled: "reviews",
+        };
+        let document = parse_query("{ users { username reviews } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let clock = MockClock::default();
+        let mut fut = Executor::new(&schema, coordinator)
+            .options(ExecutorOptions {
+                total_timeout: Some(std::time::Duration::from_secs(10)),
+                ..Default::default()
+            })
+            .clock(clock.clone())
+            .execute(&plan)
+            .boxed_local();
+
+        assert!((&mut fut).now_or_never().is_none());
+        clock.advance(std::time::Duration::from_secs(10));
+        let resp = fut.now_or_never().unwrap();
+        assert_eq!(resp.data, value!({ "users": [{ "username": "a" }] }));
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Operation exceeded 10000 ms.");
+    }
+
+    #[test]
+    fn split_coalesced_aliases() {
+        let schema = combine(&[(
+            "accounts",
+            "type Query { user(id: ID!): User } type User { id: ID! name: String! email: String! }",
+        )]);
+        let coordinator = MockCoordinator(vec![(
+            "accounts",
+            "{a:user (id: 1) {name email}",
+            value!({
+                "a": { "name": "a", "email": "a@example.com" },
+                "c": { "name": "c" }
+            }),
+        )]);
+        let resp = execute(
+            &schema,
+            "{ a: user(id: 1) { name } b: user(id: 1) { email } c: user(id: 2) { name } }",
+            coordinator,
+            Default::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "a": { "name": "a" },
+                "b": { "email": "a@example.com" },
+                "c": { "name": "c" }
+            })
+        );
+    }
+
+    #[test]
+    fn disable_introspection() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let coordinator = || MockCoordinator(vec![("accounts", "", value!({ "me": "a" }))]);
+        let options = || ExecutorOptions {
+            introspection_enabled: false,
+            ..Default::default()
+        };
+
+        let resp = execute(
+            &schema,
+            "{ __schema { queryType { name } } }",
+            coordinator(),
+            options(),
+        );
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Introspection is disabled.");
+
+        let resp = execute(&schema, "{ __typename me }", coordinator(), options());
+        assert!(resp.errors.is_empty());
+        assert_eq!(resp.data, value!({ "__typename": "Query", "me": "a" }));
+    }
+
+    #[test]
+    fn introspection_hides_inaccessible_fields() {
+        let schema = combine(&[(
+            "accounts",
+            r#"type Query { me: User } type User @key(fields: "id") { id: ID! @inaccessible name: String }"#,
+        )]);
+        let resp = execute(
+            &schema,
+            r#"{ __type(name: "User") { fields { name } } }"#,
+            MockCoordinator(Vec::new()),
+            Default::default(),
+        );
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({ "__type": { "fields": [{ "name": "name" }] } })
+        );
+    }
+
+    #[test]
+    fn empty_subgraph_response() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let resp = execute(
+            &schema,
+            "{ me }",
+            MockCoordinator(vec![("accounts", "", ConstValue::Null)]),
+            Default::default(),
+        );
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Subgraph 'accounts' returned neither data nor errors."
+        );
+        assert_eq!(resp.errors[0].path, vec![ErrorPath::Name("me".to_string())]);
+
+        let resp = execute(
+            &schema,
+            "{ me }",
+            MockCoordinator(vec![("accounts", "", ConstValue::Null)]),
+            ExecutorOptions {
+                allow_empty_responses: true,
+                ..Default::default()
+            },
+        );
+        assert!(resp.errors.is_empty());
+    }
+
+    #[test]
+    fn introspection_depth_limit() {
+        let schema = combine(&[("accounts", "type Query { me: [String!]! }")]);
+        let type_ref = |depth: usize| {
+            let mut selection = "kind name".to_string();
+            for _ in 0..depth {
+                selection = format!("kind name ofType {{ {} }}", selection);
+            }
+            format!(
+                r#"{{ __type(name: "Query") {{ fields {{ type {{ {} }} }} }} }}"#,
+                selection
+            )
+        };
+
+        let resp = execute(
+            &schema,
+            &type_ref(8),
+            MockCoordinator(Vec::new()),
+            Default::default(),
+        );
+        assert!(resp.errors.is_empty());
+
+        let resp = execute(
+            &schema,
+            &type_ref(64),
+            MockCoordinator(Vec::new()),
+            Default::default(),
+        );
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Introspection query exceeds the maximum depth of 15."
+        );
+
+        let resp = execute(
+            &schema,
+            &type_ref(2),
+            MockCoordinator(Vec::new()),
+            ExecutorOptions {
+                max_introspection_depth: Some(3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(resp.errors.len(), 1);
+    }
+
+    #[test]
+    fn canonical_introspection_query() {
+        let schema = combine(&[("accounts", "type Query { me: [String!]! }")]);
+        let query = r#"
+            query IntrospectionQuery {
+                __schema {
+                    queryType { name }
+                    mutationType { name }
+                    subscriptionType { name }
+                    types { ...FullType }
+                    directives { name description locations args { ...InputValue } }
+                }
+            }
+            fragment FullType on __Type {
+                kind name description
+                fields(includeDeprecated: true) {
+                    name description args { ...InputValue } type { ...TypeRef }
+                    isDeprecated deprecationReason
+                }
+                inputFields { ...InputValue }
+                interfaces { ...TypeRef }
+                enumValues(includeDeprecated: true) {
+                    name description isDeprecated deprecationReason
+                }
+                possibleTypes { ...TypeRef }
+            }
+            fragment InputValue on __InputValue {
+                name description type { ...TypeRef } defaultValue
+            }
+            fragment TypeRef on __Type {
+                kind name ofType { kind name ofType { kind name ofType { kind name
+                ofType { kind name ofType { kind name ofType { kind name
+                ofType { kind name } } } } } } }
+            }
+        "#;
+        let resp = execute(
+            &schema,
+            query,
+            MockCoordinator(Vec::new()),
+            Default::default(),
+        );
+        assert!(resp.errors.is_empty());
+        let query_type = match &resp.data {
+            ConstValue::Object(data) => match data.get("__schema") {
+                Some(ConstValue::Object(schema)) => schema.get("queryType").cloned(),
+                _ => None,
+            },
+            _ => None,
+        };
+        assert_eq!(query_type, Some(value!({ "name": "Query" })));
+    }
+
+    #[test]
+    fn remove_key_fields_from_partial_data() {
+        let mut data = value!({
+            "users": [{ "username": "a", "__graphgate_key1_id": "1" }],
+            "__graphgate_key2_id": "2"
+        });
+        remove_key_fields(&mut data);
+        assert_eq!(data, value!({ "users": [{ "username": "a" }] }));
+    }
+
+    struct RecordingCoordinator {
+        inner: MockCoordinator,
+        requests: Mutex<Vec<(String, Variables)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Coordinator for RecordingCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            service: &str,
+            query: &str,
+            variables: Variables,
+            headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            self.requests
+                .lock()
+                .push((service.to_string(), variables.clone()));
+            self.inner.query(service, query, variables, headers).await
+        }
+    }
+
+    #[test]
+    fn batch_sibling_flatten_nodes() {
+        let schema = users_reviews_schema_with("type Query { me: User topUsers: [User!]! }", &[]);
+        let coordinator = Arc::new(RecordingCoordinator {
+            inner: MockCoordinator(vec![
+                (
+                    "accounts",
+                    "",
+                    value!({
+                        "me": { "username": "a", "__graphgate_key1___typename": "User", "__graphgate_key1_id": "1" },
+                        "topUsers": [
+                            { "username": "b", "__graphgate_key2___typename": "User", "__graphgate_key2_id": "2" },
+                            { "username": "c", "__graphgate_key2___typename": "User", "__graphgate_key2_id": "3" }
+                        ]
+                    }),
+                ),
+                (
+                    "reviews",
+                    "",
+                    value!({ "_entities": [{ "reviews": ["x"] }, { "reviews": ["y"] }, { "reviews": [] }] }),
+                ),
+            ]),
+            requests: Default::default(),
+        });
+        let document =
+            parse_query("{ me { username reviews } topUsers { username reviews } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({
+                "me": { "username": "a", "reviews": ["x"] },
+                "topUsers": [
+                    { "username": "b", "reviews": ["y"] },
+                    { "username": "c", "reviews": [] }
+                ]
+            })
+        );
+        let requests = coordinator.requests.lock();
+        let reviews = requests
+            .iter()
+            .filter(|(service, _)| service == "reviews")
+            .collect::<Vec<_>>();
+        assert_eq!(reviews.len(), 1);
+        assert_eq!(
+            reviews[0].1["representations"],
+            value!([
+                { "__typename": "User", "id": "1" },
+                { "__typename": "User", "id": "2" },
+                { "__typename": "User", "id": "3" }
+            ])
+        );
+    }
+
+    #[test]
+    fn minimal_representations() {
+        let schema = combine(&[
+            (
+                "accounts",
+                r#"type Query { users: [User!]! }
+                type Org { id: ID! name: String! }
+                type User @key(fields: "id org { id }") { id: ID! org: Org! username: String! }"#,
+            ),
+            (
+                "reviews",
+                r#"extend type User @key(fields: "id org { id }") {
+                    id: ID! @external
+                    org: Org! @external
+                    username: String! @external
+                    reviews: [String!]! @requires(fields: "username")
+                }"#,
+            ),
+        ]);
+        let coordinator = Arc::new(RecordingCoordinator {
+            inner: MockCoordinator(vec![
+                (
+                    "accounts",
+                    "",
+                    value!({
+                        "users": [{
+                            "id": "1",
+                            "__graphgate_key1___typename": "User",
+                            "__graphgate_key1_id": "1",
+                            "__graphgate_key1_org": { "id": "o1" },
+                            "__graphgate_key1_username": "a"
+                        }]
+                    }),
+                ),
+                (
+                    "reviews",
+                    "",
+                    value!({ "_entities": [{ "reviews": ["good"] }] }),
+                ),
+            ]),
+            requests: Default::default(),
+        });
+        let document = parse_query("{ users { id reviews } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(
+            resp.data,
+            value!({ "users": [{ "id": "1", "reviews": ["good"] }] })
+        );
+        let requests = coordinator.requests.lock();
+        let (_, variables) = requests
+            .iter()
+            .find(|(service, _)| service == "reviews")
+            .unwrap();
+        assert_eq!(
+            variables["representations"],
+            value!([{ "__typename": "User", "id": "1", "org": { "id": "o1" }, "username": "a" }])
+        );
+    }
+
+    fn execute_requires(products: ConstValue) -> (Response, Vec<(String, Variables)>) {
+        let schema = combine(&[
+            (
+                "products",
+                r#"type Product @key(fields: "upc") { upc: String! weight: Int! }"#,
+            ),
+            (
+                "reviews",
+                r#"type Query { topReviews: [Review!]! }
+                type Review { product: Product! }
+                extend type Product @key(fields: "upc") { upc: String! @external }"#,
+            ),
+            (
+                "inventory",
+                r#"extend type Product @key(fields: "upc") {
+                    upc: String! @external
+                    weight: Int! @external
+                    shippingEstimate: Int @requires(fields: "weight")
+                }"#,
+            ),
+        ]);
+        let coordinator = Arc::new(RecordingCoordinator {
+            inner: MockCoordinator(vec![
+                (
+                    "reviews",
+                    "",
+                    value!({
+                        "topReviews": [{
+                            "product": { "__graphgate_key1___typename": "Product", "__graphgate_key1_upc": "p1" }
+                        }]
+                    }),
+                ),
+                ("products", "", products),
+                (
+                    "inventory",
+                    "",
+                    value!({ "_entities": [{ "shippingEstimate": 5 }] }),
+                ),
+            ]),
+            requests: Default::default(),
+        });
+        let document = parse_query("{ topReviews { product { shippingEstimate } } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+        let requests = coordinator.requests.lock().clone();
+        (resp, requests)
+    }
+
+    #[test]
+    fn requires_resolved_before_entity_fetch() {
+        let (resp, requests) = execute_requires(value!({
+            "_entities": [{
+                "__graphgate_key2___typename": "Product",
+                "__graphgate_key2_upc": "p1",
+                "__graphgate_key2_weight": 10
+            }]
+        }));
+        assert!(resp.errors.is_empty());
+        assert_eq!(
+            resp.data,
+            value!({ "topReviews": [{ "product": { "shippingEstimate": 5 } }] })
+        );
+        let (_, variables) = requests
+            .iter()
+            .find(|(service, _)| service == "inventory")
+            .unwrap();
+        assert_eq!(
+            variables["representations"],
+            value!([{ "__typename": "Product", "upc": "p1", "weight": 10 }])
+        );
+
+        let (resp, requests) = execute_requires(value!({ "_entities": [null] }));
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Entity 'Product' is missing the required field 'weight' for service 'inventory'."
+        );
+        assert_eq!(
+            resp.errors[0].path,
+            vec![
+                ErrorPath::Name("topReviews".to_string()),
+                ErrorPath::Index(0),
+                ErrorPath::Name("product".to_string()),
+            ]
+        );
+        assert!(!requests.iter().any(|(service, _)| service == "inventory"));
+    }
+
+    #[derive(Default)]
+    struct ConcurrencyCoordinator {
+        current: AtomicUsize,
+        max: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Coordinator for ConcurrencyCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            _service: &str,
+            _query: &str,
+            _variables: Variables,
+            _headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max.fetch_max(current, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            self.current.fetch_sub(1, Ordering::SeqCst);
+            Ok(Response {
+                data: ConstValue::Null,
+                errors: Vec::new(),
+                extensions: Default::default(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn parallel_max_concurrency() {
+        let schema = combine(&[
+            ("a", "type Query { a: Int }"),
+            ("b", "type Query { b: Int }"),
+            ("c", "type Query { c: Int }"),
+        ]);
+        let document = parse_query("{ a b c }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+
+        for (max_concurrency, expected) in &[(None, 3), (Some(2), 2), (Some(1), 1)] {
+            let coordinator = Arc::new(ConcurrencyCoordinator::default());
+            Executor::new(&schema, coordinator.clone())
+                .options(ExecutorOptions {
+                    max_concurrency: *max_concurrency,
+                    ..Default::default()
+                })
+                .execute(&plan)
+                .await;
+            assert_eq!(coordinator.max.load(Ordering::SeqCst), *expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn coalesce_identical_requests() {
+        let schema = combine(&[("a", "type Query { a: Int }")]);
+
+        for (retryable, expected) in &[(true, 1), (false, 2)] {
+            let coordinator = Arc::new(ConcurrencyCoordinator::default());
+            let executor = Executor::new(&schema, coordinator.clone());
+            let (a, b) = futures_util::future::join(
+                executor.query_service("a", "{ a }", Variables::default(), *retryable),
+                executor.query_service("a", "{ a }", Variables::default(), *retryable),
+            )
+            .await;
+            assert!(a.is_ok() && b.is_ok());
+            assert_eq!(coordinator.max.load(Ordering::SeqCst), *expected);
+            assert!(executor.in_flight.lock().is_empty());
+        }
+    }
+
+    #[derive(Default)]
+    struct HeadersCoordinator(Mutex<Vec<(String, String)>>);
+
+    #[async_trait::async_trait]
+    impl Coordinator for HeadersCoordinator {
+        type Error = std::convert::Infallible;
+
+        async fn query(
+            &self,
+            _service: &str,
+            _query: &str,
+            _variables: Variables,
+            headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            self.0.lock().extend(headers.iter().cloned());
+            Ok(Response {
+                data: ConstValue::Null,
+                errors: Vec::new(),
+                extensions: Default::default(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn forward_headers() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let document = parse_query("{ me }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let coordinator = Arc::new(HeadersCoordinator::default());
+        Executor::new(&schema, coordinator.clone())
+            .options(ExecutorOptions {
+                forward_headers: vec!["authorization".to_string()],
+                ..Default::default()
+            })
+            .headers(vec![
+                ("Authorization".to_string(), "Bearer abc".to_string()),
+                ("cookie".to_string(), "session=1".to_string()),
+            ])
+            .execute(&plan)
+            .await;
+        assert_eq!(
+            coordinator.0.lock().clone(),
+            vec![("Authorization".to_string(), "Bearer abc".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn response_interceptors() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let plan = PlanBuilder::new(&schema, parse_query("{ me }").unwrap())
+            .plan()
+            .unwrap();
+        let resp = Executor::new(
+            &schema,
+            MockCoordinator(vec![("accounts", "", value!({ "me": "alice" }))]),
+        )
+        .interceptor(|_service: &str, resp: &mut Response| {
+            if let ConstValue::Object(data) = &mut resp.data {
+                data.insert(Name::new("me"), value!("redacted"));
+            }
+        })
+        .interceptor(|service: &str, resp: &mut Response| {
+            if let ConstValue::Object(data) = &mut resp.data {
+                if let Some(ConstValue::String(me)) = data.get_mut("me") {
+                    *me = format!("{}:{}", service, me);
+                }
+            }
+        })
+        .execute(&plan)
+        .await;
+        assert_eq!(resp.data, value!({ "me": "accounts:redacted" }));
+    }
+
+    #[tokio::test]
+    async fn subgraph_error_service_name() {
+        let schema = combine(&[("accounts", "type Query { me: String }")]);
+        let plan = PlanBuilder::new(&schema, parse_query("{ me }").unwrap())
+            .plan()
+            .unwrap();
+        let resp = Executor::new(&schema, MockCoordinator(Vec::new()))
+            .interceptor(|_service: &str, resp: &mut Response| {
+                let mut extensions = BTreeMap::new();
+                extensions.insert("code".to_string(), value!("INTERNAL"));
+                resp.errors.push(ServerError {
+                    message: "Boom.".to_string(),
+                    locations: Default::default(),
+                    path: Default::default(),
+                    extensions,
+                });
+            })
+            .execute(&plan)
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Boom.");
+        assert_eq!(
+            resp.errors[0].extensions.get("serviceName"),
+            Some(&value!("accounts"))
+        );
+        assert_eq!(
+            resp.errors[0].extensions.get("code"),
+            Some(&value!("INTERNAL"))
+        );
+    }
+
+    #[test]
+    fn max_representations() {
+        let schema = users_reviews_schema_with(
+            "type Query { users: [User!]! }",
+            &[(
+                "products",
+                r#"extend type User @key(fields: "id") { id: ID! @external purchases: [String!]! }"#,
+            )],
+        );
+        let coordinator = Arc::new(RecordingCoordinator {
+            inner: MockCoordinator(vec![(
+                "accounts",
+                "",
+                value!({
+                    "users": [
+                        {
+                            "__graphgate_key1___typename": "User",
+                            "__graphgate_key1_id": "1",
+                            "__graphgate_key2___typename": "User",
+                            "__graphgate_key2_id": "1",
+                        },
+                        {
+                            "__graphgate_key1___typename": "User",
+                            "__graphgate_key1_id": "2",
+                            "__graphgate_key2___typename": "User",
+                            "__graphgate_key2_id": "2",
+                        },
+                    ]
+                }),
+            )]),
+            requests: Default::default(),
+        });
+        let document = parse_query("{ users { reviews purchases } }").unwrap();
+        let plan = PlanBuilder::new(&schema, document).plan().unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .options(ExecutorOptions {
+                max_representations: Some(3),
+                ..Default::default()
+            })
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(
+            resp.errors[0].message,
+            "Operation requires at least 4 entity representations, which exceeds the limit of 3."
+        );
+        let entity_requests = coordinator
+            .requests
+            .lock()
+            .iter()
+            .filter(|(service, _)| service != "accounts")
+            .count();
+        assert_eq!(entity_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn execute_stream_deferred() {
+        let schema = combine(&[
+            ("accounts", "type Query { me: String }"),
+            ("products", "type Query { topProducts: [String!]! }"),
+        ]);
+        let query = r#"{ me ... @defer(label: "products") { topProducts } }"#;
+        let coordinator = || {
+            MockCoordinator(vec![
+                ("accounts", "", value!({ "me": "alice" })),
+                ("products", "", value!({ "topProducts": ["a", "b"] })),
+            ])
+        };
+        let plan = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .plan()
+            .unwrap();
+
+        let responses = Executor::new(&schema, coordinator())
+            .execute_stream(&plan)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].response.data, value!({ "me": "alice" }));
+        assert_eq!(responses[0].label, None);
+        assert_eq!(responses[0].path, None);
+        assert!(responses[0].has_next);
+        assert_eq!(
+            responses[1].response.data,
+            value!({ "topProducts": ["a", "b"] })
+        );
+        assert_eq!(responses[1].label.as_deref(), Some("products"));
+        assert_eq!(responses[1].path, Some(Vec::new()));
+        assert!(!responses[1].has_next);
+
+        let resp = Executor::new(&schema, coordinator()).execute(&plan).await;
+        assert_eq!(
+            resp.data,
+            value!({ "me": "alice", "topProducts": ["a", "b"] })
+        );
+    }
+
+    #[test]
+    fn execute_stream_total_timeout() {
+        let schema = combine(&[
+            ("accounts", "type Query { me: String }"),
+            ("products", "type Query { topProducts: [String!]! }"),
+        ]);
+        let query = r#"{ me ... @defer(label: "products") { topProducts } }"#;
+        let coordinator = StalledCoordinator {
+            inner: MockCoordinator(vec![("accounts", "", value!({ "me": "alice" }))]),
+            stalled: "products",
+        };
+        let plan = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .plan()
+            .unwrap();
+        let clock = MockClock::default();
+        let mut responses = Executor::new(&schema, coordinator)
+            .options(ExecutorOptions {
+                total_timeout: Some(std::time::Duration::from_secs(10)),
+                ..Default::default()
+            })
+            .clock(clock.clone())
+            .execute_stream(&plan);
+
+        let response = responses.next().now_or_never().unwrap().unwrap();
+        assert_eq!(response.response.data, value!({ "me": "alice" }));
+        assert!(response.has_next);
+
+        clock.advance(std::time::Duration::from_secs(5));
+        assert!(responses.next().now_or_never().is_none());
+        clock.advance(std::time::Duration::from_secs(5));
+        let response = responses.next().now_or_never().unwrap().unwrap();
+        assert_eq!(response.label.as_deref(), Some("products"));
+        assert!(!response.has_next);
+        assert_eq!(response.response.errors.len(), 1);
+        assert_eq!(
+            response.response.errors[0].message,
+            "Operation exceeded 10000 ms."
+        );
+        assert!(responses.next().now_or_never().unwrap().is_none());
+    }
+
+    #[derive(Default)]
+    struct FlakyCoordinator {
+        failures: AtomicUsize,
+        calls: AtomicUsize,
+        permanent: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Coordinator for FlakyCoordinator {
+        type Error = String;
+
+        async fn query(
+            &self,
+            _service: &str,
+            _query: &str,
+            _variables: Variables,
+            _headers: &[(String, String)],
+        ) -> Result<Response, Self::Error> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err("Connection reset.".to_string());
+            }
+            Ok(Response {
+                data: value!({ "a": 1 }),
+                errors: Vec::new(),
+                extensions: Default::default(),
+            })
+        }
+
+        fn is_retryable(&self, _err: &Self::Error) -> bool {
+            !self.permanent
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_transient_errors() {
+        let schema = combine(&[("a", "type Query { a: Int } type Mutation { b: Int }")]);
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_millis(0),
+            ..Default::default()
+        };
+
+        for (failures, calls, errors) in &[(2, 3, 0), (3, 3, 1)] {
+            let coordinator = Arc::new(FlakyCoordinator::default());
+            coordinator.failures.store(*failures, Ordering::SeqCst);
+            let plan = PlanBuilder::new(&schema, parse_query("{ a }").unwrap())
+                .plan()
+                .unwrap();
+            let resp = Executor::new(&schema, coordinator.clone())
+                .options(ExecutorOptions {
+                    retry: Some(retry.clone()),
+                    ..Default::default()
+                })
+                .execute(&plan)
+                .await;
+            assert_eq!(coordinator.calls.load(Ordering::SeqCst), *calls);
+            assert_eq!(resp.errors.len(), *errors);
+        }
+
+        let coordinator = Arc::new(FlakyCoordinator {
+            permanent: true,
+            ..Default::default()
+        });
+        coordinator.failures.store(1, Ordering::SeqCst);
+        let plan = PlanBuilder::new(&schema, parse_query("{ a }").unwrap())
+            .plan()
+            .unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .options(ExecutorOptions {
+                retry: Some(retry.clone()),
+                ..Default::default()
+            })
+            .execute(&plan)
+            .await;
+        assert_eq!(coordinator.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(resp.errors.len(), 1);
+
+        let coordinator = Arc::new(FlakyCoordinator::default());
+        coordinator.failures.store(1, Ordering::SeqCst);
+        let plan = PlanBuilder::new(&schema, parse_query("mutation { b }").unwrap())
+            .plan()
+            .unwrap();
+        let resp = Executor::new(&schema, coordinator.clone())
+            .options(ExecutorOptions {
+                retry: Some(retry),
+                ..Default::default()
+            })
+            .execute(&plan)
+            .await;
+        assert_eq!(coordinator.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(resp.errors.len(), 1);
+    }
+
+    #[test]
+    fn retry_budget_mock_clock() {
+        let schema = combine(&[("a", "type Query { a: Int }")]);
+        let plan = PlanBuilder::new(&schema, parse_query("{ a }").unwrap())
+            .plan()
+            .unwrap();
+        let clock = MockClock::default();
+        let retry_budget = Arc::new(
+            RetryBudget::new(0.0)
+                .min_per_second(1.0)
+                .capacity(1.0)
+                .clock(clock.clone()),
+        );
+        let coordinator = Arc::new(FlakyCoordinator::default());
+        coordinator.failures.store(usize::MAX, Ordering::SeqCst);
+        let execute = || {
+            Executor::new(&schema, coordinator.clone())
+                .options(ExecutorOptions {
+                    retry: Some(RetryPolicy {
+                        max_attempts: 3,
+                        base_delay: std::time::Duration::from_millis(0),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .clock(clock.clone())
+                .retry_budget(retry_budget.clone())
+                .execute(&plan)
+                .now_or_never()
+                .unwrap();
+            coordinator.calls.swap(0, Ordering::SeqCst)
+        };
+
+        assert_eq!(execute(), 2);
+        assert_eq!(execute(), 1);
+        clock.advance(std::time::Duration::from_millis(500));
+        assert_eq!(execute(), 1);
+        clock.advance(std::time::Duration::from_millis(500));
+        assert_eq!(execute(), 2);
+        assert_eq!(execute(), 1);
+
+        let retry_budget = Arc::new(
+            RetryBudget::new(0.5)
+                .min_per_second(0.0)
+                .capacity(1.0)
+                .clock(clock.clone()),
+        );
+        let calls = (0..4)
+            .map(|_| {
+                Executor::new(&schema, coordinator.clone())
+                    .options(ExecutorOptions {
+                        retry: Some(RetryPolicy {
+                            max_attempts: 2,
+                            base_delay: std::time::Duration::from_millis(0),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                    .clock(clock.clone())
+                    .retry_budget(retry_budget.clone())
+                    .execute(&plan)
+                    .now_or_never()
+                    .unwrap();
+                coordinator.calls.swap(0, Ordering::SeqCst)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(calls, vec![2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn retry_delay() {
+        let retry = RetryPolicy {
+            max_attempts: 5,
+            base_delay: std::time::Duration::from_millis(100),
+            max_delay: std::time::Duration::from_millis(300),
+            jitter: false,
+        };
+        assert_eq!(retry.delay(1), std::time::Duration::from_millis(100));
+        assert_eq!(retry.delay(2), std::time::Duration::from_millis(200));
+        assert_eq!(retry.delay(3), std::time::Duration::from_millis(300));
+        assert_eq!(retry.delay(64), std::time::Duration::from_millis(300));
+        assert_eq!(
+            RetryPolicy {
+                jitter: false,
+                ..Default::default()
+            }
+            .delay(usize::MAX),
+            std::time::Duration::from_secs(10)
+        );
+
+        let retry = RetryPolicy {
+            jitter: true,
+            ..retry
+        };
+        let delay = retry.delay(2);
+        assert!(delay >= std::time::Duration::from_millis(100));
+        assert!(delay <= std::time::Duration::from_millis(200));
+    }
+
+    #[test]
+    fn fetch_cache_hits() {
+        let schema = combine(&[(
+            "accounts",
+            "type Query { me: String @cacheControl(maxAge: 60) version: String }",
+        )]);
+        let fetch_cache = Arc::new(MemoryFetchCache::new(16));
+
+        for (query, calls) in &[("{ me }", 1), ("{ version }", 2), ("{ me version }", 2)] {
+            let coordinator = Arc::new(RecordingCoordinator {
+                inner: MockCoordinator(vec![(
+                    "accounts",
+                    "",
+                    value!({ "me": "a", "version": "1" }),
+                )]),
+                requests: Default::default(),
+            });
+            for _ in 0..2 {
+                let plan = PlanBuilder::new(&schema, parse_query(query).unwrap())
+                    .plan()
+                    .unwrap();
+                let resp = Executor::new(&schema, coordinator.clone())
+                    .fetch_cache(fetch_cache.clone())
+                    .execute(&plan)
+                    .now_or_never()
+                    .unwrap();
+                assert!(resp.errors.is_empty());
+            }
+            assert_eq!(coordinator.requests.lock().len(), *calls);
+        }
+
+        let plan = PlanBuilder::new(&schema, parse_query("{ me }").unwrap())
+            .plan()
+            .unwrap();
+        let resp = Executor::new(&schema, MockCoordinator(Vec::new()))
+            .options(ExecutorOptions {
+                max_request_bytes: Some(0),
+                ..Default::default()
+            })
+            .fetch_cache(fetch_cache)
+            .execute(&plan)
+            .now_or_never()
+            .unwrap();
+        assert_eq!(resp.data, value!({ "me": "a" }));
+        assert!(resp.errors.is_empty());
     }
 }
@@ -0,0 +1,122 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone)]
+pub struct ServiceOptions {
+    pub max_body_size: Option<usize>,
+    pub optional: bool,
+    pub strict_entities: bool,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMergePolicy {
+    Truncate,
+    Pad,
+    Replace,
+    Error,
+}
+
+impl Default for ListMergePolicy {
+    fn default() -> Self {
+        ListMergePolicy::Error
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn delay(&self, attempt: usize) -> Duration {
+        let shift = attempt.saturating_sub(1).min(31) as u32;
+        let mut delay = self
+            .base_delay
+            .checked_mul(1 << shift)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if self.jitter {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(attempt);
+            let ratio = (hasher.finish() % 1000) as u32;
+            delay = delay / 2 + delay / 2000 * ratio;
+        }
+        delay
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutorOptions {
+    pub services: HashMap<String, ServiceOptions>,
+    pub max_request_bytes: Option<usize>,
+    pub list_merge_policy: ListMergePolicy,
+    pub timeout: Option<Duration>,
+    pub total_timeout: Option<Duration>,
+    pub expose_sources: bool,
+    pub max_concurrency: Option<usize>,
+    pub forward_headers: Vec<String>,
+    pub retry: Option<RetryPolicy>,
+    pub max_representations: Option<usize>,
+    pub max_introspection_depth: Option<usize>,
+    pub introspection_enabled: bool,
+    pub allow_empty_responses: bool,
+}
+
+impl Default for ExecutorOptions {
+    fn default() -> Self {
+        Self {
+            services: Default::default(),
+            max_request_bytes: None,
+            list_merge_policy: Default::default(),
+            timeout: None,
+            total_timeout: None,
+            expose_sources: false,
+            max_concurrency: None,
+            forward_headers: Vec::new(),
+            retry: None,
+            max_representations: None,
+            max_introspection_depth: None,
+            introspection_enabled: true,
+            allow_empty_responses: false,
+        }
+    }
+}
+
+impl ExecutorOptions {
+    pub fn service(mut self, service: impl Into<String>, options: ServiceOptions) -> Self {
+        self.services.insert(service.into(), options);
+        self
+    }
+
+    #[inline]
+    pub(crate) fn service_options(&self, service: &str) -> Option<&ServiceOptions> {
+        self.services.get(service)
+    }
+
+    #[inline]
+    pub(crate) fn service_timeout(&self, service: &str) -> Option<Duration> {
+        self.service_options(service)
+            .and_then(|options| options.timeout)
+            .or(self.timeout)
+    }
+}
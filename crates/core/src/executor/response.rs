@@ -1,26 +1,75 @@
+use std::collections::BTreeMap;
+
 use parser::Pos;
 use serde::{Deserialize, Serialize};
 use value::ConstValue;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ErrorPath {
     Name(String),
     Index(usize),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ErrorPath {
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            ErrorPath::Name(name) => Some(name),
+            ErrorPath::Index(_) => None,
+        }
+    }
+
+    pub fn as_index(&self) -> Option<usize> {
+        match self {
+            ErrorPath::Name(_) => None,
+            ErrorPath::Index(idx) => Some(*idx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerError {
     pub message: String,
 
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub locations: Vec<Pos>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub path: Vec<ErrorPath>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub extensions: BTreeMap<String, ConstValue>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl ServerError {
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.path.iter().filter_map(ErrorPath::as_name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response {
+    #[serde(default)]
     pub data: ConstValue,
 
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub errors: Vec<ServerError>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub extensions: BTreeMap<String, ConstValue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementalResponse {
+    #[serde(flatten)]
+    pub response: Response,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<ErrorPath>>,
+
+    pub has_next: bool,
 }
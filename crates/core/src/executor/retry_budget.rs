@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use spin::Mutex;
+
+use crate::{Clock, TokioClock};
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+pub struct RetryBudget {
+    ratio: f64,
+    min_per_second: f64,
+    capacity: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl RetryBudget {
+    pub fn new(ratio: f64) -> Self {
+        Self {
+            ratio: ratio.max(0.0),
+            min_per_second: 1.0,
+            capacity: 10.0,
+            buckets: Default::default(),
+            clock: Arc::new(TokioClock),
+        }
+    }
+
+    pub fn min_per_second(self, min_per_second: f64) -> Self {
+        Self {
+            min_per_second: min_per_second.max(0.0),
+            ..self
+        }
+    }
+
+    pub fn capacity(self, capacity: f64) -> Self {
+        Self {
+            capacity: capacity.max(1.0),
+            ..self
+        }
+    }
+
+    pub fn clock(self, clock: impl Clock + 'static) -> Self {
+        Self {
+            clock: Arc::new(clock),
+            ..self
+        }
+    }
+
+    pub(crate) fn deposit(&self, service: &str) {
+        self.update(service, |tokens| *tokens += self.ratio);
+    }
+
+    pub(crate) fn withdraw(&self, service: &str) -> bool {
+        self.update(service, |tokens| {
+            if *tokens >= 1.0 {
+                *tokens -= 1.0;
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    fn update<R>(&self, service: &str, f: impl FnOnce(&mut f64) -> R) -> R {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets
+            .entry(service.to_string())
+            .or_insert_with(|| Bucket {
+                tokens: self.capacity,
+                updated_at: now,
+            });
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens += elapsed.as_secs_f64() * self.min_per_second;
+        bucket.updated_at = now;
+        let res = f(&mut bucket.tokens);
+        bucket.tokens = bucket.tokens.min(self.capacity);
+        res
+    }
+}
@@ -3,6 +3,12 @@ mod planner;
 mod schema;
 mod validation;
 
-pub use executor::{Coordinator, ErrorPath, Executor, Response, ServerError};
-pub use planner::PlanBuilder;
-pub use schema::{CombineError, ComposedSchema};
+pub use executor::{
+    Clock, Coordinator, ErrorPath, Executor, ExecutorOptions, FetchCache, IncrementalResponse,
+    ListMergePolicy, MemoryFetchCache, MockClock, Response, ResponseInterceptor, RetryBudget,
+    RetryPolicy, ServerError, ServiceOptions, TokioClock,
+};
+pub use planner::{
+    ComputedFields, OperationInfo, PlanBuilder, PlanCache, PlanCacheKey, SubgraphQuery,
+};
+pub use schema::{CombineError, ComposedSchema, CompositionError};
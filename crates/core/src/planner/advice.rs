@@ -0,0 +1,158 @@
+use super::plan::PlanNode;
+
+const MAX_SEQUENTIAL_FLATTENS: usize = 2;
+const MAX_NESTED_LISTS: usize = 1;
+
+impl<'a> PlanNode<'a> {
+    pub fn warnings(&self) -> Vec<String> {
+        fn sequential_flattens(node: &PlanNode<'_>) -> usize {
+            match node {
+                PlanNode::Sequence(sequence) => {
+                    sequence.nodes.iter().map(sequential_flattens).sum()
+                }
+                PlanNode::Parallel(parallel) => parallel
+                    .nodes
+                    .iter()
+                    .map(sequential_flattens)
+                    .max()
+                    .unwrap_or_default(),
+                PlanNode::Flatten(_) => 1,
+                PlanNode::Defer(defer) => std::iter::once(&*defer.primary)
+                    .chain(defer.deferred.iter().map(|deferred| &deferred.node))
+                    .map(sequential_flattens)
+                    .max()
+                    .unwrap_or_default(),
+                PlanNode::Fetch(_)
+                | PlanNode::Introspection(_)
+                | PlanNode::Compute(_)
+                | PlanNode::NullCheck(_) => 0,
+            }
+        }
+
+        fn collect_warnings(warnings: &mut Vec<String>, node: &PlanNode<'_>) {
+            match node {
+                PlanNode::Sequence(sequence) => sequence
+                    .nodes
+                    .iter()
+                    .for_each(|node| collect_warnings(warnings, node)),
+                PlanNode::Parallel(parallel) => parallel
+                    .nodes
+                    .iter()
+                    .for_each(|node| collect_warnings(warnings, node)),
+                PlanNode::Flatten(flatten) => {
+                    for (path, _) in flatten.paths() {
+                        let lists = path.iter().filter(|segment| segment.is_list).count();
+                        if lists > MAX_NESTED_LISTS {
+                            warnings.push(format!(
+                                "Entities of type '{}' are fetched from service '{}' for every element of {} nested lists at '{}', consider selecting them at a shallower level.",
+                                flatten.parent_type, flatten.service, lists, path
+                            ));
+                        }
+                    }
+                }
+                PlanNode::Defer(defer) => {
+                    collect_warnings(warnings, &defer.primary);
+                    defer
+                        .deferred
+                        .iter()
+                        .for_each(|deferred| collect_warnings(warnings, &deferred.node));
+                }
+                PlanNode::Fetch(_)
+                | PlanNode::Introspection(_)
+                | PlanNode::Compute(_)
+                | PlanNode::NullCheck(_) => {}
+            }
+        }
+
+        let mut warnings = Vec::new();
+        let hops = sequential_flattens(self);
+        if hops > MAX_SEQUENTIAL_FLATTENS {
+            warnings.push(format!(
+                "Operation requires {} sequential entity fetches, consider selecting fewer nested fields owned by other services.",
+                hops
+            ));
+        }
+        collect_warnings(&mut warnings, self);
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::{parse_query, parse_schema};
+
+    use crate::{ComposedSchema, PlanBuilder};
+
+    #[test]
+    fn nested_list_entities() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { users: [User!]! }
+                    type User @key(fields: "id") { id: ID! friends: [User!]! }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "reviews".to_string(),
+                parse_schema(
+                    r#"extend type User @key(fields: "id") { id: ID! @external reviewCount: Int! }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        let plan_builder =
+            PlanBuilder::new(&schema, parse_query("{ users { reviewCount } }").unwrap());
+        assert!(plan_builder.plan().unwrap().warnings().is_empty());
+
+        let plan_builder = PlanBuilder::new(
+            &schema,
+            parse_query("{ users { friends { reviewCount } } }").unwrap(),
+        );
+        let warnings = plan_builder.plan().unwrap().warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].starts_with("Entities of type 'User' are fetched from service 'reviews'")
+        );
+    }
+
+    #[test]
+    fn sequential_flattens() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "a".to_string(),
+                parse_schema(
+                    r#"type Query { a: A }
+                    type A @key(fields: "id") { id: ID! }
+                    extend type B @key(fields: "id") { id: ID! @external a: A }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "b".to_string(),
+                parse_schema(
+                    r#"extend type A @key(fields: "id") { id: ID! @external b: B }
+                    type B @key(fields: "id") { id: ID! }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        let plan_builder =
+            PlanBuilder::new(&schema, parse_query("{ a { b { a { id } } } }").unwrap());
+        assert!(plan_builder.plan().unwrap().warnings().is_empty());
+
+        let plan_builder = PlanBuilder::new(
+            &schema,
+            parse_query("{ a { b { a { b { id } } } } }").unwrap(),
+        );
+        assert_eq!(
+            plan_builder.plan().unwrap().warnings(),
+            vec!["Operation requires 3 sequential entity fetches, consider selecting fewer nested fields owned by other services.".to_string()]
+        );
+    }
+}
@@ -1,32 +1,47 @@
 #![allow(clippy::too_many_arguments)]
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::time::Duration;
 
 use indexmap::IndexMap;
 use parser::types::{
-    BaseType, DocumentOperations, ExecutableDocument, Field, FragmentDefinition, InlineFragment,
-    OperationDefinition, OperationType, Selection, SelectionSet, Type,
+    BaseType, Directive, DocumentOperations, ExecutableDocument, Field, FragmentDefinition,
+    InlineFragment, OperationDefinition, OperationType, Selection, SelectionSet, Type,
 };
 use parser::Positioned;
 use value::{ConstValue, Name, Value, Variables};
 
+use super::cache::PlanCacheKey;
+use super::complexity::compute_complexity;
+use super::computed::ComputedFields;
+use super::operation_info::OperationInfo;
 use super::plan::{
-    FetchNode, FlattenNode, IntrospectionDirective, IntrospectionField, IntrospectionNode,
-    IntrospectionSelectionSet, ParallelNode, PathSegment, PlanNode, ResponsePath, SequenceNode,
+    BatchedPath, ComputeField, ComputeNode, DeferNode, DeferredNode, FetchNode, FlattenNode,
+    IntrospectionDirective, IntrospectionField, IntrospectionNode, IntrospectionSelectionSet,
+    NullCheckNode, ParallelNode, PathSegment, PlanNode, ResponseField, ResponsePath, SequenceNode,
 };
+use super::preview::SubgraphQuery;
+use super::selection_width::check_selection_width;
 use super::types::{
     FetchEntity, FetchEntityGroup, FetchEntityKey, FieldRef, RequiredRef, RootGroup, SelectionRef,
     SelectionRefSet,
 };
 use crate::schema::{ComposedSchema, KeyFields, MetaField, MetaType, TypeKind};
-use crate::validation::check_rules;
+use crate::validation::{check_rules, check_warnings};
 use crate::{Response, ServerError};
 
+const DEFAULT_MAX_VARIABLES: usize = 1024;
+const DEFAULT_MAX_FRAGMENTS: usize = 1024;
+
 struct Context<'a> {
     schema: &'a ComposedSchema,
     fragments: &'a HashMap<Name, Positioned<FragmentDefinition>>,
-    variables: &'a Variables,
+    variables: Variables,
+    computed_fields: Option<&'a ComputedFields>,
+    compute_fields: Vec<ComputeField<'a>>,
     key_id: usize,
+    authorized: bool,
 }
 
 pub struct PlanBuilder<'a> {
@@ -34,6 +49,13 @@ pub struct PlanBuilder<'a> {
     document: ExecutableDocument,
     operation_name: Option<String>,
     variables: Variables,
+    computed_fields: Option<&'a ComputedFields>,
+    max_variables: usize,
+    max_fragments: usize,
+    max_selection_width: Option<usize>,
+    max_root_fields: Option<usize>,
+    max_complexity: Option<usize>,
+    authorized: bool,
 }
 
 impl<'a> PlanBuilder<'a> {
@@ -43,6 +65,13 @@ impl<'a> PlanBuilder<'a> {
             document,
             operation_name: None,
             variables: Default::default(),
+            computed_fields: None,
+            max_variables: DEFAULT_MAX_VARIABLES,
+            max_fragments: DEFAULT_MAX_FRAGMENTS,
+            max_selection_width: None,
+            max_root_fields: None,
+            max_complexity: None,
+            authorized: false,
         }
     }
 
@@ -55,28 +84,237 @@ impl<'a> PlanBuilder<'a> {
         Self { variables, ..self }
     }
 
+    pub fn computed_fields(self, computed_fields: &'a ComputedFields) -> Self {
+        Self {
+            computed_fields: Some(computed_fields),
+            ..self
+        }
+    }
+
+    pub fn max_variables(self, max_variables: usize) -> Self {
+        Self {
+            max_variables,
+            ..self
+        }
+    }
+
+    pub fn max_fragments(self, max_fragments: usize) -> Self {
+        Self {
+            max_fragments,
+            ..self
+        }
+    }
+
+    pub fn max_selection_width(self, max_selection_width: usize) -> Self {
+        Self {
+            max_selection_width: Some(max_selection_width),
+            ..self
+        }
+    }
+
+    pub fn max_root_fields(self, max_root_fields: usize) -> Self {
+        Self {
+            max_root_fields: Some(max_root_fields),
+            ..self
+        }
+    }
+
+    pub fn max_complexity(self, max_complexity: usize) -> Self {
+        Self {
+            max_complexity: Some(max_complexity),
+            ..self
+        }
+    }
+
+    pub fn authorized(self, authorized: bool) -> Self {
+        Self { authorized, ..self }
+    }
+
+    pub fn operation_info(&self) -> Option<OperationInfo> {
+        OperationInfo::new(self.schema, &self.document, self.operation_name.as_deref())
+    }
+
+    pub fn complexity(&self) -> Option<usize> {
+        find_operation(&self.document, self.operation_name.as_deref()).map(|(_, operation)| {
+            compute_complexity(&self.document.fragments, &operation.node.selection_set.node)
+        })
+    }
+
+    pub fn cache_key(&self, query: &str) -> PlanCacheKey {
+        PlanCacheKey {
+            query: query.to_string(),
+            operation_name: self.operation_name.clone(),
+            variables: self
+                .variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            authorized: self.authorized,
+            max_complexity: self.max_complexity,
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        check_warnings(self.schema, &self.document, &self.variables)
+            .into_iter()
+            .map(|err| err.message)
+            .collect()
+    }
+
+    pub fn preview(&self) -> Result<Vec<SubgraphQuery>, Response> {
+        self.plan().map(|plan| plan.subgraph_queries())
+    }
+
     pub fn plan(&self) -> Result<PlanNode, Response> {
+        let count = self.document.fragments.len();
+        if count > self.max_fragments {
+            return Err(Response {
+                data: ConstValue::Null,
+                extensions: Default::default(),
+                errors: vec![ServerError {
+                    message: format!(
+                        "Document defines {} fragments, which exceeds the limit of {}.",
+                        count, self.max_fragments
+                    ),
+                    locations: Default::default(),
+                    path: Default::default(),
+                    extensions: Default::default(),
+                }],
+            });
+        }
+
+        let operation_definition =
+            match find_operation(&self.document, self.operation_name.as_deref()) {
+                Some((_, operation)) => operation,
+                None => {
+                    let message = match &self.operation_name {
+                    Some(operation_name) => {
+                        format!("Unknown operation named '{}'.", operation_name)
+                    }
+                    None => {
+                        "Operation name is required when the document contains multiple operations."
+                            .to_string()
+                    }
+                };
+                    return Err(Response {
+                        data: ConstValue::Null,
+                        extensions: Default::default(),
+                        errors: vec![ServerError {
+                            message,
+                            locations: Default::default(),
+                            path: Default::default(),
+                            extensions: Default::default(),
+                        }],
+                    });
+                }
+            };
+
+        let count = operation_definition.node.variable_definitions.len();
+        if count > self.max_variables {
+            return Err(Response {
+                data: ConstValue::Null,
+                extensions: Default::default(),
+                errors: vec![ServerError {
+                    message: format!(
+                        "Operation declares {} variables, which exceeds the limit of {}.",
+                        count, self.max_variables
+                    ),
+                    locations: vec![operation_definition.pos],
+                    path: Default::default(),
+                    extensions: Default::default(),
+                }],
+            });
+        }
+
         let rule_errors = check_rules(self.schema, &self.document, &self.variables);
         if !rule_errors.is_empty() {
             return Err(Response {
                 data: ConstValue::Null,
+                extensions: Default::default(),
                 errors: rule_errors
                     .into_iter()
                     .map(|err| ServerError {
                         message: err.message,
                         locations: err.locations,
+                        path: Default::default(),
+                        extensions: Default::default(),
                     })
                     .collect(),
             });
         }
 
         let fragments = &self.document.fragments;
-        let operation_definition = get_operation(&self.document, self.operation_name.as_deref());
+        if let Some(max_complexity) = self.max_complexity {
+            let complexity =
+                compute_complexity(fragments, &operation_definition.node.selection_set.node);
+            if complexity > max_complexity {
+                return Err(Response {
+                    data: ConstValue::Null,
+                    errors: vec![ServerError {
+                        message: format!(
+                            "Operation has a cost of {}, which exceeds the budget of {}.",
+                            complexity, max_complexity
+                        ),
+                        locations: vec![operation_definition.pos],
+                        path: Default::default(),
+                        extensions: Default::default(),
+                    }],
+                    extensions: Default::default(),
+                });
+            }
+        }
+        if let Some(max_selection_width) = self.max_selection_width {
+            if let Some(err) = check_selection_width(
+                fragments,
+                &operation_definition.node.selection_set,
+                max_selection_width,
+            ) {
+                return Err(Response {
+                    data: ConstValue::Null,
+                    errors: vec![err],
+                    extensions: Default::default(),
+                });
+            }
+        }
+        if let Some(max_root_fields) = self.max_root_fields {
+            let count = self
+                .operation_info()
+                .map(|info| info.root_fields.len())
+                .unwrap_or_default();
+            if count > max_root_fields {
+                return Err(Response {
+                    data: ConstValue::Null,
+                    errors: vec![ServerError {
+                        message: format!(
+                            "Operation has {} root fields, which exceeds the limit of {}.",
+                            count, max_root_fields
+                        ),
+                        locations: vec![operation_definition.pos],
+                        path: Default::default(),
+                        extensions: Default::default(),
+                    }],
+                    extensions: Default::default(),
+                });
+            }
+        }
+        let variables = match coerce_variables(&operation_definition.node, &self.variables) {
+            Ok(variables) => variables,
+            Err(errors) => {
+                return Err(Response {
+                    data: ConstValue::Null,
+                    errors,
+                    extensions: Default::default(),
+                })
+            }
+        };
         let mut ctx = Context {
             schema: self.schema,
             fragments,
-            variables: &self.variables,
+            variables,
+            computed_fields: self.computed_fields,
+            compute_fields: Vec::new(),
             key_id: 1,
+            authorized: self.authorized,
         };
 
         let root_type = match operation_definition.node.ty {
@@ -91,8 +329,35 @@ impl<'a> PlanBuilder<'a> {
         };
 
         if let Some(root_type) = ctx.schema.types.get(root_type) {
-            Ok(ctx
-                .build_root_selection_set(root_type, &operation_definition.node.selection_set.node))
+            let mut deferred_selection_sets = Vec::new();
+            let primary = ctx.build_root_selection_set(
+                &mut deferred_selection_sets,
+                root_type,
+                &operation_definition.node.selection_set.node,
+            );
+            if deferred_selection_sets.is_empty() {
+                return Ok(primary);
+            }
+
+            let mut deferred = Vec::new();
+            let mut idx = 0;
+            while idx < deferred_selection_sets.len() {
+                let (label, selection_set) = deferred_selection_sets[idx];
+                let node = ctx.build_root_selection_set(
+                    &mut deferred_selection_sets,
+                    root_type,
+                    selection_set,
+                );
+                deferred.push(DeferredNode {
+                    label: label.map(Cow::Borrowed),
+                    node,
+                });
+                idx += 1;
+            }
+            Ok(PlanNode::Defer(DeferNode {
+                primary: Box::new(primary),
+                deferred,
+            }))
         } else {
             unreachable!("The query validator should find this error.")
         }
@@ -102,11 +367,13 @@ impl<'a> PlanBuilder<'a> {
 impl<'a> Context<'a> {
     fn build_root_selection_set(
         &mut self,
+        deferred: &mut Vec<(Option<&'a str>, &'a SelectionSet)>,
         parent_type: &'a MetaType,
         selection_set: &'a SelectionSet,
     ) -> PlanNode<'a> {
         fn build_root_selection_set_rec<'a>(
             ctx: &mut Context<'a>,
+            deferred: &mut Vec<(Option<&'a str>, &'a SelectionSet)>,
             root_group: &mut RootGroup<'a>,
             fetch_entity_group: &mut FetchEntityGroup<'a>,
             inspection_selection_set: &mut IntrospectionSelectionSet,
@@ -114,9 +381,16 @@ impl<'a> Context<'a> {
             selection_set: &'a SelectionSet,
         ) {
             for selection in &selection_set.items {
+                if ctx.is_skipped(&selection.node) {
+                    continue;
+                }
                 match &selection.node {
                     Selection::Field(field) => {
                         let field_name = field.node.name.node.as_str();
+                        if field_name == "__typename" {
+                            ctx.build_introspection_field(inspection_selection_set, &field.node);
+                            continue;
+                        }
 
                         let field_definition = match parent_type.fields.get(field_name) {
                             Some(field_definition) => field_definition,
@@ -130,6 +404,14 @@ impl<'a> Context<'a> {
                             ctx.build_introspection_field(inspection_selection_set, &field.node);
                             continue;
                         }
+                        if ctx.mask_field(
+                            &ResponsePath::default(),
+                            parent_type,
+                            field_definition,
+                            &field.node,
+                        ) {
+                            continue;
+                        }
 
                         if let Some(service) = &field_definition.service {
                             let selection_ref_set = root_group.entry(service).or_default();
@@ -149,8 +431,16 @@ impl<'a> Context<'a> {
                             .fragments
                             .get(fragment_spread.node.fragment_name.node.as_str())
                         {
+                            if let Some(defer) =
+                                ctx.defer_directive(&fragment_spread.node.directives)
+                            {
+                                deferred
+                                    .push((defer_label(defer), &fragment.node.selection_set.node));
+                                continue;
+                            }
                             build_root_selection_set_rec(
                                 ctx,
+                                deferred,
                                 root_group,
                                 fetch_entity_group,
                                 inspection_selection_set,
@@ -160,8 +450,16 @@ impl<'a> Context<'a> {
                         }
                     }
                     Selection::InlineFragment(inline_fragment) => {
+                        if let Some(defer) = ctx.defer_directive(&inline_fragment.node.directives) {
+                            deferred.push((
+                                defer_label(defer),
+                                &inline_fragment.node.selection_set.node,
+                            ));
+                            continue;
+                        }
                         build_root_selection_set_rec(
                             ctx,
+                            deferred,
                             root_group,
                             fetch_entity_group,
                             inspection_selection_set,
@@ -178,6 +476,7 @@ impl<'a> Context<'a> {
         let mut inspection_selection_set = IntrospectionSelectionSet::default();
         build_root_selection_set_rec(
             self,
+            deferred,
             &mut root_group,
             &mut fetch_entity_group,
             &mut inspection_selection_set,
@@ -188,16 +487,34 @@ impl<'a> Context<'a> {
         let mut nodes = Vec::new();
         if !inspection_selection_set.0.is_empty() {
             nodes.push(PlanNode::Introspection(IntrospectionNode {
+                root_type: parent_type.name.clone(),
                 selection_set: inspection_selection_set,
             }));
         }
 
         let fetch_node = {
+            let is_mutation = self.schema.mutation_type() == Some(parent_type.name.as_str());
             let mut nodes = Vec::new();
             for (service, selection_set) in root_group {
+                let response_keys = selection_set.response_keys();
+                let (selection_set, aliases) = if is_mutation {
+                    (selection_set, Vec::new())
+                } else {
+                    selection_set.coalesce_aliases(&self.variables)
+                };
+                let cache_ttl = if is_mutation {
+                    None
+                } else {
+                    self.cache_max_age(parent_type, &selection_set)
+                        .map(Duration::from_secs)
+                };
                 nodes.push(PlanNode::Fetch(FetchNode {
-                    service,
-                    query: selection_set.to_query(self.variables),
+                    service: Cow::Borrowed(service),
+                    query: selection_set.to_query(&self.variables),
+                    is_mutation,
+                    response_keys,
+                    aliases,
+                    cache_ttl,
                 }));
             }
             PlanNode::Parallel(ParallelNode { nodes }).flatten()
@@ -205,7 +522,7 @@ impl<'a> Context<'a> {
         nodes.push(fetch_node);
 
         while !fetch_entity_group.is_empty() {
-            let mut flatten_nodes = Vec::new();
+            let mut flatten_nodes: Vec<FlattenNode<'_>> = Vec::new();
             let mut next_group = FetchEntityGroup::new();
 
             for (
@@ -216,10 +533,17 @@ impl<'a> Context<'a> {
                     parent_type,
                     prefix,
                     fields,
+                    required,
+                    dependencies,
+                    dependents,
                 },
             ) in fetch_entity_group
             {
                 let mut selection_ref_set = SelectionRefSet::default();
+                let response_keys = fields
+                    .iter()
+                    .map(|field| Cow::Borrowed(field.response_key().node.as_str()))
+                    .collect();
 
                 for field in fields {
                     self.build_field(
@@ -231,33 +555,231 @@ impl<'a> Context<'a> {
                         field,
                     );
                 }
+                selection_ref_set
+                    .0
+                    .extend(dependencies.into_iter().map(SelectionRef::RequiredRef));
 
                 let query = format!(
                     "query($representations:[_Any!]!) {{ _entities(representations:$representations) {{ ... on {} {} }} }}",
                     parent_type.name,
-                    selection_ref_set.to_query(self.variables)
+                    selection_ref_set.to_query(&self.variables)
                 );
-                flatten_nodes.push(PlanNode::Flatten(FlattenNode {
-                    path,
-                    prefix,
-                    service,
-                    parent_type: parent_type.name.as_str(),
-                    query,
-                }));
+                match flatten_nodes
+                    .iter_mut()
+                    .find(|flatten| flatten.service == service && flatten.query == query)
+                {
+                    Some(flatten) => flatten.batched.push(BatchedPath { path, prefix }),
+                    None => flatten_nodes.push(FlattenNode {
+                        path,
+                        prefix,
+                        service: Cow::Borrowed(service),
+                        parent_type: Cow::Borrowed(parent_type.name.as_str()),
+                        response_keys,
+                        required: required.into_iter().map(Cow::Borrowed).collect(),
+                        query,
+                        batched: Vec::new(),
+                    }),
+                }
+
+                for (key, dependent) in dependents {
+                    match next_group.get_mut(&key) {
+                        Some(fetch_entity) => {
+                            fetch_entity.fields.extend(dependent.fields);
+                            fetch_entity.required.extend(dependent.required);
+                        }
+                        None => {
+                            next_group.insert(key, dependent);
+                        }
+                    }
+                }
             }
 
             nodes.push(
                 PlanNode::Parallel(ParallelNode {
-                    nodes: flatten_nodes,
+                    nodes: flatten_nodes.into_iter().map(PlanNode::Flatten).collect(),
                 })
                 .flatten(),
             );
             fetch_entity_group = next_group;
         }
 
+        if !self.compute_fields.is_empty() {
+            nodes.push(PlanNode::Compute(ComputeNode {
+                fields: std::mem::take(&mut self.compute_fields),
+            }));
+        }
+
+        let mut response_fields = Vec::new();
+        self.build_response_fields(&mut response_fields, parent_type, selection_set);
+        if !response_fields.is_empty() {
+            nodes.push(PlanNode::NullCheck(NullCheckNode {
+                fields: response_fields,
+            }));
+        }
+
         PlanNode::Sequence(SequenceNode { nodes }).flatten()
     }
 
+    fn cache_max_age(
+        &self,
+        parent_type: &'a MetaType,
+        selection_set: &SelectionRefSet<'_>,
+    ) -> Option<u64> {
+        fn lower_max_age(
+            schema: &ComposedSchema,
+            parent_type: &MetaType,
+            selection_set: &SelectionRefSet<'_>,
+            max_age: &mut u64,
+        ) {
+            for selection in &selection_set.0 {
+                match selection {
+                    SelectionRef::FieldRef(field_ref) => {
+                        if let Some(field_definition) =
+                            parent_type.field_by_name(&field_ref.field.name.node)
+                        {
+                            if let Some(field_max_age) = field_definition.cache_max_age {
+                                *max_age = (*max_age).min(field_max_age);
+                            }
+                            if let Some(field_type) = schema.get_type(&field_definition.ty) {
+                                lower_max_age(
+                                    schema,
+                                    field_type,
+                                    &field_ref.selection_set,
+                                    max_age,
+                                );
+                            }
+                        }
+                    }
+                    SelectionRef::InlineFragment {
+                        type_condition,
+                        selection_set,
+                    } => {
+                        let ty = type_condition
+                            .and_then(|type_condition| schema.types.get(type_condition))
+                            .unwrap_or(parent_type);
+                        lower_max_age(schema, ty, selection_set, max_age);
+                    }
+                    SelectionRef::IntrospectionTypename | SelectionRef::RequiredRef(_) => {}
+                }
+            }
+        }
+
+        let mut max_age = u64::MAX;
+        for selection in &selection_set.0 {
+            if let SelectionRef::FieldRef(field_ref) = selection {
+                let field_definition = parent_type.field_by_name(&field_ref.field.name.node)?;
+                max_age = max_age.min(field_definition.cache_max_age?);
+            }
+        }
+        lower_max_age(self.schema, parent_type, selection_set, &mut max_age);
+        Some(max_age).filter(|max_age| *max_age > 0 && *max_age < u64::MAX)
+    }
+
+    fn mask_field(
+        &mut self,
+        path: &ResponsePath<'a>,
+        parent_type: &'a MetaType,
+        field_definition: &'a MetaField,
+        field: &'a Field,
+    ) -> bool {
+        if self.authorized || !field_definition.masked {
+            return false;
+        }
+        self.compute_fields.push(ComputeField {
+            path: path.clone(),
+            parent_type: Cow::Borrowed(parent_type.name.as_str()),
+            response_key: Cow::Borrowed(field.response_key().node.as_str()),
+            resolver: None,
+        });
+        true
+    }
+
+    fn build_response_fields(
+        &self,
+        fields: &mut Vec<ResponseField<'a>>,
+        parent_type: &'a MetaType,
+        selection_set: &'a SelectionSet,
+    ) {
+        for selection in &selection_set.items {
+            if self.is_skipped(&selection.node) {
+                continue;
+            }
+            match &selection.node {
+                Selection::FragmentSpread(fragment_spread)
+                    if self
+                        .defer_directive(&fragment_spread.node.directives)
+                        .is_some() => {}
+                Selection::InlineFragment(inline_fragment)
+                    if self
+                        .defer_directive(&inline_fragment.node.directives)
+                        .is_some() => {}
+                Selection::Field(field) => {
+                    let field_definition = match parent_type
+                        .fields
+                        .get(field.node.name.node.as_str())
+                    {
+                        Some(field_definition) if self.authorized || !field_definition.masked => {
+                            field_definition
+                        }
+                        _ => continue,
+                    };
+                    let mut selection_set = Vec::new();
+                    if let Some(field_type) = self.schema.get_type(&field_definition.ty) {
+                        if field_type.is_composite() {
+                            self.build_response_fields(
+                                &mut selection_set,
+                                field_type,
+                                &field.node.selection_set.node,
+                            );
+                        }
+                    }
+                    if has_non_null(&field_definition.ty) || !selection_set.is_empty() {
+                        fields.push(ResponseField {
+                            parent_type: Cow::Borrowed(parent_type.name.as_str()),
+                            field_name: Cow::Borrowed(field_definition.name.as_str()),
+                            response_key: Cow::Borrowed(field.node.response_key().node.as_str()),
+                            ty: Cow::Borrowed(&field_definition.ty),
+                            selection_set,
+                        });
+                    }
+                }
+                Selection::FragmentSpread(fragment_spread) => {
+                    if let Some(fragment) = self
+                        .fragments
+                        .get(fragment_spread.node.fragment_name.node.as_str())
+                    {
+                        if let Some(ty) = self
+                            .schema
+                            .types
+                            .get(fragment.node.type_condition.node.on.node.as_str())
+                        {
+                            self.build_response_fields(
+                                fields,
+                                ty,
+                                &fragment.node.selection_set.node,
+                            );
+                        }
+                    }
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    let ty = match &inline_fragment.node.type_condition {
+                        Some(type_condition) => {
+                            self.schema.types.get(type_condition.node.on.node.as_str())
+                        }
+                        None => Some(parent_type),
+                    };
+                    if let Some(ty) = ty {
+                        self.build_response_fields(
+                            fields,
+                            ty,
+                            &inline_fragment.node.selection_set.node,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     fn build_introspection_field(
         &mut self,
         introspection_selection_set: &mut IntrospectionSelectionSet,
@@ -269,6 +791,9 @@ impl<'a> Context<'a> {
             selection_set: &'a SelectionSet,
         ) {
             for selection in &selection_set.items {
+                if ctx.is_skipped(&selection.node) {
+                    continue;
+                }
                 match &selection.node {
                     Selection::Field(field) => {
                         ctx.build_introspection_field(introspection_selection_set, &field.node);
@@ -310,7 +835,7 @@ impl<'a> Context<'a> {
                             .clone()
                             .into_const_with(|name| {
                                 Ok::<_, std::convert::Infallible>(
-                                    ctx.variables.get(&name).unwrap().clone(),
+                                    ctx.variables.get(&name).cloned().unwrap_or_default(),
                                 )
                             })
                             .unwrap(),
@@ -359,29 +884,29 @@ impl<'a> Context<'a> {
             Some(field_definition) => field_definition,
             None => return,
         };
-        let field_type = match self.schema.get_type(&field_definition.ty) {
-            Some(field_type) => field_type,
-            None => return,
-        };
 
-        if field_type.kind == TypeKind::Interface {
-            path.push(PathSegment {
-                name: field.response_key().node.as_str(),
-                is_list: is_list(&field_definition.ty),
-                possible_type: None,
+        if self.mask_field(path, parent_type, field_definition, field) {
+            return;
+        }
+
+        if let Some(resolver) = self
+            .computed_fields
+            .and_then(|computed_fields| computed_fields.get(&parent_type.name, field_name))
+        {
+            self.compute_fields.push(ComputeField {
+                path: path.clone(),
+                parent_type: Cow::Borrowed(parent_type.name.as_str()),
+                response_key: Cow::Borrowed(field.response_key().node.as_str()),
+                resolver: Some(Cow::Borrowed(resolver)),
             });
-            self.build_interface_field(
-                path,
-                selection_ref_set,
-                fetch_entity_group,
-                current_service,
-                &field,
-                &field_type,
-            );
-            path.pop();
             return;
         }
 
+        let field_type = match self.schema.get_type(&field_definition.ty) {
+            Some(field_type) => field_type,
+            None => return,
+        };
+
         let service = match field_definition
             .service
             .as_deref()
@@ -407,6 +932,7 @@ impl<'a> Context<'a> {
                     path,
                     selection_ref_set,
                     fetch_entity_group,
+                    current_service,
                     parent_type,
                     field,
                     &field_definition,
@@ -419,31 +945,71 @@ impl<'a> Context<'a> {
 
         let mut sub_selection_set = SelectionRefSet::default();
         path.push(PathSegment {
-            name: field.response_key().node.as_str(),
+            name: Cow::Borrowed(field.response_key().node.as_str()),
             is_list: is_list(&field_definition.ty),
             possible_type: None,
         });
 
-        self.build_selection_set(
-            path,
-            &mut sub_selection_set,
-            fetch_entity_group,
-            current_service,
-            field_type,
-            &field.selection_set.node,
-        );
+        if field_type.is_abstract() {
+            sub_selection_set
+                .0
+                .push(SelectionRef::IntrospectionTypename);
+            self.build_interface_field(
+                path,
+                &mut sub_selection_set,
+                fetch_entity_group,
+                current_service,
+                field,
+                field_type,
+            );
+        } else {
+            self.build_selection_set(
+                path,
+                &mut sub_selection_set,
+                fetch_entity_group,
+                current_service,
+                field_type,
+                &field.selection_set.node,
+            );
+        }
         path.pop();
         selection_ref_set.0.push(SelectionRef::FieldRef(FieldRef {
             field,
+            defaults: self.enum_argument_defaults(field_definition, field),
             selection_set: sub_selection_set,
         }));
     }
 
+    fn enum_argument_defaults(
+        &self,
+        field_definition: &'a MetaField,
+        field: &'a Field,
+    ) -> Vec<(&'a Name, &'a ConstValue)> {
+        field_definition
+            .arguments
+            .values()
+            .filter(|arg| {
+                !field
+                    .arguments
+                    .iter()
+                    .any(|(name, _)| name.node == arg.name)
+            })
+            .filter(|arg| {
+                matches!(
+                    self.schema.concrete_type_by_name(&arg.ty),
+                    Some(ty) if ty.kind == TypeKind::Enum
+                )
+            })
+            .filter_map(|arg| Some((&arg.name, arg.default_value.as_ref()?)))
+            .collect()
+    }
+
     fn add_fetch_entity(
         &mut self,
         path: &mut ResponsePath<'a>,
         selection_ref_set: &mut SelectionRefSet<'a>,
         fetch_entity_group: &mut FetchEntityGroup<'a>,
+        current_service: &'a str,
         parent_type: &'a MetaType,
         field: &'a Field,
         meta_field: &'a MetaField,
@@ -456,9 +1022,58 @@ impl<'a> Context<'a> {
             ty: parent_type.name.as_str(),
         };
 
+        if let Some((owner, owner_keys)) =
+            self.requires_owner(current_service, parent_type, meta_field)
+        {
+            // The required fields have to be fetched from their owner first, so the
+            // entity fetch for this field moves one step later in the sequence.
+            let owner_key = FetchEntityKey {
+                service: owner,
+                path: path.clone(),
+                ty: parent_type.name.as_str(),
+            };
+            if !fetch_entity_group.contains_key(&owner_key) {
+                let prefix = self.take_key_prefix();
+                selection_ref_set
+                    .0
+                    .push(SelectionRef::RequiredRef(RequiredRef {
+                        prefix,
+                        fields: owner_keys,
+                        requires: None,
+                    }));
+                fetch_entity_group.insert(
+                    owner_key.clone(),
+                    FetchEntity::new(parent_type, prefix).require(owner_keys, None),
+                );
+            }
+            let owner_entity = &mut fetch_entity_group[&owner_key];
+            let prefix = match owner_entity.dependents.get_mut(&fetch_entity_key) {
+                Some(dependent) => {
+                    dependent.fields.push(field);
+                    dependent.add_required(keys, meta_field.requires.as_ref());
+                    dependent.prefix
+                }
+                None => {
+                    let prefix = self.take_key_prefix();
+                    let mut dependent = FetchEntity::new(parent_type, prefix)
+                        .require(keys, meta_field.requires.as_ref());
+                    dependent.fields.push(field);
+                    owner_entity.dependents.insert(fetch_entity_key, dependent);
+                    prefix
+                }
+            };
+            owner_entity.dependencies.push(RequiredRef {
+                prefix,
+                fields: keys,
+                requires: meta_field.requires.as_ref(),
+            });
+            return;
+        }
+
         match fetch_entity_group.get_mut(&fetch_entity_key) {
             Some(fetch_entity) => {
                 fetch_entity.fields.push(field);
+                fetch_entity.add_required(keys, meta_field.requires.as_ref());
                 selection_ref_set
                     .0
                     .push(SelectionRef::RequiredRef(RequiredRef {
@@ -476,18 +1091,44 @@ impl<'a> Context<'a> {
                         fields: keys,
                         requires: meta_field.requires.as_ref(),
                     }));
-                fetch_entity_group.insert(
-                    fetch_entity_key,
-                    FetchEntity {
-                        parent_type,
-                        prefix,
-                        fields: vec![field],
-                    },
-                );
+                let mut fetch_entity = FetchEntity::new(parent_type, prefix)
+                    .require(keys, meta_field.requires.as_ref());
+                fetch_entity.fields.push(field);
+                fetch_entity_group.insert(fetch_entity_key, fetch_entity);
             }
         }
     }
 
+    fn requires_owner(
+        &self,
+        current_service: &str,
+        parent_type: &'a MetaType,
+        meta_field: &MetaField,
+    ) -> Option<(&'a str, &'a KeyFields)> {
+        let requires = meta_field.requires.as_ref()?;
+        let owner = parent_type.owner.as_deref()?;
+        if owner == current_service {
+            return None;
+        }
+        let current_keys = parent_type.keys.get(current_service);
+        let resolvable = requires.keys().all(|name| {
+            let service = parent_type
+                .fields
+                .get(name)
+                .and_then(|field| field.service.as_deref())
+                .unwrap_or(owner);
+            service == current_service
+                || current_keys.map_or(false, |keys| {
+                    keys.iter().any(|keys| keys.contains_key(name))
+                })
+        });
+        if resolvable {
+            return None;
+        }
+        let owner_keys = parent_type.keys.get(owner).and_then(|keys| keys.get(0))?;
+        Some((owner, owner_keys))
+    }
+
     fn build_selection_set(
         &mut self,
         path: &mut ResponsePath<'a>,
@@ -498,6 +1139,9 @@ impl<'a> Context<'a> {
         selection_set: &'a SelectionSet,
     ) {
         for selection in &selection_set.items {
+            if self.is_skipped(&selection.node) {
+                continue;
+            }
             match &selection.node {
                 Selection::Field(field) => {
                     self.build_field(
@@ -557,6 +1201,9 @@ impl<'a> Context<'a> {
             possible_type: &'a MetaType,
         ) {
             for selection in &selection_set.items {
+                if ctx.is_skipped(&selection.node) {
+                    continue;
+                }
                 match &selection.node {
                     Selection::Field(field) => {
                         ctx.build_field(
@@ -624,7 +1271,8 @@ impl<'a> Context<'a> {
             possible_type: &'a MetaType,
         ) {
             let mut sub_selection_set = SelectionRefSet::default();
-            path.last_mut().unwrap().possible_type = Some(possible_type.name.as_str());
+            path.last_mut().unwrap().possible_type =
+                Some(Cow::Borrowed(possible_type.name.as_str()));
             build_fields_rec(
                 ctx,
                 path,
@@ -650,6 +1298,9 @@ impl<'a> Context<'a> {
             selection_set: &'a SelectionSet,
         ) {
             for selection in &selection_set.items {
+                if ctx.is_skipped(&selection.node) {
+                    continue;
+                }
                 match &selection.node {
                     Selection::FragmentSpread(fragment_spread) => {
                         if let Some(fragment) = ctx
@@ -704,7 +1355,7 @@ impl<'a> Context<'a> {
 
         for possible_type in &field_type.possible_types {
             if let Some(ty) = self.schema.types.get(possible_type) {
-                path.last_mut().unwrap().possible_type = Some(ty.name.as_str());
+                path.last_mut().unwrap().possible_type = Some(Cow::Borrowed(ty.name.as_str()));
                 build_fields(
                     self,
                     path,
@@ -728,6 +1379,49 @@ impl<'a> Context<'a> {
         );
     }
 
+    fn is_skipped(&self, selection: &Selection) -> bool {
+        let directives = match selection {
+            Selection::Field(field) => &field.node.directives,
+            Selection::FragmentSpread(fragment_spread) => &fragment_spread.node.directives,
+            Selection::InlineFragment(inline_fragment) => &inline_fragment.node.directives,
+        };
+        directives.iter().any(|directive| {
+            let skip_if = match directive.node.name.node.as_str() {
+                "skip" => true,
+                "include" => false,
+                _ => return false,
+            };
+            self.directive_condition(&directive.node)
+                .unwrap_or_default()
+                == skip_if
+        })
+    }
+
+    fn defer_directive(&self, directives: &'a [Positioned<Directive>]) -> Option<&'a Directive> {
+        directives
+            .iter()
+            .map(|directive| &directive.node)
+            .find(|directive| {
+                directive.name.node.as_str() == "defer"
+                    && self.directive_condition(directive).unwrap_or(true)
+            })
+    }
+
+    fn directive_condition(&self, directive: &Directive) -> Option<bool> {
+        directive
+            .arguments
+            .iter()
+            .find(|(name, _)| name.node.as_str() == "if")
+            .and_then(|(_, value)| match &value.node {
+                Value::Boolean(value) => Some(*value),
+                Value::Variable(name) => match self.variables.get(name) {
+                    Some(ConstValue::Boolean(value)) => Some(*value),
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
+
     fn take_key_prefix(&mut self) -> usize {
         let id = self.key_id;
         self.key_id += 1;
@@ -782,28 +1476,739 @@ impl<'a> Context<'a> {
     }
 }
 
+fn coerce_variables(
+    operation_definition: &OperationDefinition,
+    variables: &Variables,
+) -> Result<Variables, Vec<ServerError>> {
+    let mut coerced_variables = variables.clone();
+    let mut errors = Vec::new();
+
+    for variable_definition in &operation_definition.variable_definitions {
+        let name = &variable_definition.node.name.node;
+        let var_type = &variable_definition.node.var_type.node;
+        match coerced_variables.remove(name) {
+            Some(ConstValue::Null) if !var_type.nullable => errors.push(ServerError {
+                message: format!(
+                    "Variable \"${}\" of non-null type \"{}\" must not be null.",
+                    name, var_type
+                ),
+                locations: vec![variable_definition.pos],
+                path: Default::default(),
+                extensions: Default::default(),
+            }),
+            Some(value) => {
+                coerced_variables.insert(name.clone(), coerce_list_value(var_type, value));
+            }
+            None => {
+                if let Some(default_value) = &variable_definition.node.default_value {
+                    coerced_variables.insert(
+                        name.clone(),
+                        coerce_list_value(var_type, default_value.node.clone()),
+                    );
+                } else if !var_type.nullable {
+                    errors.push(ServerError {
+                        message: format!(
+                            "Variable \"${}\" of required type \"{}\" was not provided.",
+                            name, var_type
+                        ),
+                        locations: vec![variable_definition.pos],
+                        path: Default::default(),
+                        extensions: Default::default(),
+                    });
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(coerced_variables)
+    } else {
+        Err(errors)
+    }
+}
+
+fn coerce_list_value(ty: &Type, value: ConstValue) -> ConstValue {
+    match (&ty.base, value) {
+        (_, ConstValue::Null) => ConstValue::Null,
+        (BaseType::List(element_ty), ConstValue::List(elements)) => ConstValue::List(
+            elements
+                .into_iter()
+                .map(|element| coerce_list_value(element_ty, element))
+                .collect(),
+        ),
+        (BaseType::List(element_ty), value) => {
+            ConstValue::List(vec![coerce_list_value(element_ty, value)])
+        }
+        (BaseType::Named(_), value) => value,
+    }
+}
+
 #[inline]
 fn is_list(ty: &Type) -> bool {
     matches!(ty.base, BaseType::List(_))
 }
 
-fn get_operation<'a>(
+fn has_non_null(ty: &Type) -> bool {
+    !ty.nullable || matches!(&ty.base, BaseType::List(ty) if has_non_null(ty))
+}
+
+pub(crate) fn find_operation<'a>(
     document: &'a ExecutableDocument,
     operation_name: Option<&str>,
-) -> &'a Positioned<OperationDefinition> {
-    let operation = if let Some(operation_name) = operation_name {
+) -> Option<(Option<&'a Name>, &'a Positioned<OperationDefinition>)> {
+    if let Some(operation_name) = operation_name {
         match &document.operations {
             DocumentOperations::Single(_) => None,
-            DocumentOperations::Multiple(operations) => operations.get(operation_name),
+            DocumentOperations::Multiple(operations) => operations
+                .get_key_value(operation_name)
+                .map(|(name, operation)| (Some(name), operation)),
         }
     } else {
         match &document.operations {
-            DocumentOperations::Single(operation) => Some(operation),
+            DocumentOperations::Single(operation) => Some((None, operation)),
             DocumentOperations::Multiple(map) if map.len() == 1 => {
-                Some(map.iter().next().unwrap().1)
+                let (name, operation) = map.iter().next().unwrap();
+                Some((Some(name), operation))
             }
             DocumentOperations::Multiple(_) => None,
         }
-    };
-    operation.expect("The query validator should find this error.")
+    }
+}
+
+fn defer_label(directive: &Directive) -> Option<&str> {
+    directive
+        .arguments
+        .iter()
+        .find(|(name, _)| name.node.as_str() == "label")
+        .and_then(|(_, value)| match &value.node {
+            Value::String(label) => Some(label.as_str()),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::{parse_query, parse_schema};
+    use value::value;
+
+    use super::*;
+
+    #[test]
+    fn abstract_field_typename_from_owning_service() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { me: User }
+                    type User @key(fields: "id") { id: ID! }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "reviews".to_string(),
+                parse_schema(
+                    r#"extend type User @key(fields: "id") { id: ID! @external lastPost: Post }
+                    interface Post { body: String! }
+                    type Review implements Post { body: String! }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+        let document = parse_query("{ me { lastPost { ... on Review { body } } } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+
+        let accounts = queries
+            .iter()
+            .find(|query| query.service == "accounts")
+            .unwrap();
+        assert!(!accounts.query.contains("lastPost"));
+        let reviews = queries
+            .iter()
+            .find(|query| query.service == "reviews")
+            .unwrap();
+        assert!(reviews
+            .query
+            .contains("lastPost {__typename ... on Review {body}}"));
+    }
+
+    #[test]
+    fn inaccessible_field_routed_to_other_service() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { me: User }
+                    type User @key(fields: "id") { id: ID! username: String @inaccessible }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "profiles".to_string(),
+                parse_schema(
+                    r#"extend type User @key(fields: "id") { id: ID! @external username: String }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            schema.types["User"].fields["username"].service.as_deref(),
+            Some("profiles")
+        );
+
+        let document = parse_query("{ me { username } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+        let accounts = queries
+            .iter()
+            .find(|query| query.service == "accounts")
+            .unwrap();
+        assert!(!accounts.query.contains("username"));
+        let profiles = queries
+            .iter()
+            .find(|query| query.service == "profiles")
+            .unwrap();
+        assert!(profiles.query.contains("username"));
+    }
+
+    #[test]
+    fn inaccessible_key_field() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { me: User }
+                    type User @key(fields: "id") { id: ID! @inaccessible name: String }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "reviews".to_string(),
+                parse_schema(
+                    r#"extend type User @key(fields: "id") { id: ID! @external reviewCount: Int }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+        assert!(schema.types["User"].fields["id"].inaccessible);
+
+        let document = parse_query("{ me { name reviewCount } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+        let accounts = queries
+            .iter()
+            .find(|query| query.service == "accounts")
+            .unwrap();
+        assert!(accounts.query.contains("id"));
+        assert!(queries.iter().any(|query| query.service == "reviews"));
+
+        let document = parse_query("{ me { id } }").unwrap();
+        let response = PlanBuilder::new(&schema, document).plan().unwrap_err();
+        assert!(response.errors[0]
+            .message
+            .starts_with("Unknown field \"id\" on type \"User\"."));
+    }
+
+    #[test]
+    fn root_fields_from_different_services() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema("type Query { me: String }").unwrap(),
+            ),
+            (
+                "products".to_string(),
+                parse_schema("type Query { topProducts: [String!]! }").unwrap(),
+            ),
+        ])
+        .unwrap();
+        let document = parse_query("{ me topProducts }").unwrap();
+        let plan_builder = PlanBuilder::new(&schema, document);
+        let mut nodes = match plan_builder.plan().unwrap() {
+            PlanNode::Sequence(SequenceNode { nodes }) => nodes,
+            node => panic!("expected a sequence node, got {:?}", node),
+        };
+        assert!(matches!(
+            &nodes[1],
+            PlanNode::NullCheck(NullCheckNode { fields }) if fields.len() == 1 && fields[0].response_key == "topProducts"
+        ));
+        let nodes = match nodes.remove(0) {
+            PlanNode::Parallel(ParallelNode { nodes }) => nodes,
+            node => panic!("expected a parallel node, got {:?}", node),
+        };
+
+        let fetches = nodes
+            .iter()
+            .map(|node| match node {
+                PlanNode::Fetch(fetch) => (fetch.service.as_ref(), fetch.query.as_str()),
+                node => panic!("expected a fetch node, got {:?}", node),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fetches,
+            vec![("accounts", "{me}"), ("products", "{topProducts}")]
+        );
+    }
+
+    #[test]
+    fn masked_fields_for_unauthorized_callers() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                r#"type Query { me: User }
+                type User { id: ID! internalNotes: String @authzMask }"#,
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+        let query = "{ me { id internalNotes } }";
+
+        let queries = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .authorized(true)
+            .preview()
+            .unwrap();
+        assert_eq!(queries[0].query, "{me {id internalNotes}}");
+
+        let plan_builder = PlanBuilder::new(&schema, parse_query(query).unwrap());
+        let queries = plan_builder.preview().unwrap();
+        assert_eq!(queries[0].query, "{me {id}}");
+        let nodes = match plan_builder.plan().unwrap() {
+            PlanNode::Sequence(SequenceNode { nodes }) => nodes,
+            node => panic!("expected a sequence node, got {:?}", node),
+        };
+        assert!(matches!(
+            &nodes[1],
+            PlanNode::Compute(ComputeNode { fields }) if fields[0].response_key == "internalNotes" && fields[0].resolver.is_none()
+        ));
+    }
+
+    #[test]
+    fn operation_name_mismatch() {
+        let schema = ComposedSchema::parse("type Query { a: Int }").unwrap();
+        for (query, operation_name) in &[("{ a }", "A"), ("query B { a }", "A")] {
+            let response = PlanBuilder::new(&schema, parse_query(*query).unwrap())
+                .operation_name(*operation_name)
+                .plan()
+                .unwrap_err();
+            assert_eq!(response.errors[0].message, "Unknown operation named 'A'.");
+        }
+    }
+
+    #[test]
+    fn operation_name_required() {
+        let schema = ComposedSchema::parse("type Query { a: Int }").unwrap();
+        let document = parse_query("query A { a } query B { a }").unwrap();
+        let response = PlanBuilder::new(&schema, document).plan().unwrap_err();
+        assert_eq!(
+            response.errors[0].message,
+            "Operation name is required when the document contains multiple operations."
+        );
+    }
+
+    #[test]
+    fn conditional_fragment_spreads() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema("type Query { me: User } type User { id: ID! name: String! }").unwrap(),
+        )])
+        .unwrap();
+        let query = r#"query($withName: Boolean!) {
+            me { id ...UserName @include(if: $withName) ... @skip(if: true) { name } }
+        }
+        fragment UserName on User { name }"#;
+
+        for (with_name, expected) in &[(true, "{me {id name}}"), (false, "{me {id}}")] {
+            let mut variables = Variables::default();
+            variables.insert(Name::new("withName"), ConstValue::Boolean(*with_name));
+            let queries = PlanBuilder::new(&schema, parse_query(query).unwrap())
+                .variables(variables)
+                .preview()
+                .unwrap();
+            assert_eq!(queries[0].query, *expected);
+        }
+    }
+
+    #[test]
+    fn conditional_fields() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                "type Query { me: User } type User { id: ID! name: String! email: String! }",
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+        let query = r#"query($withName: Boolean!) {
+            me {
+                id @skip(if: true) @include(if: true)
+                ...UserFields
+            }
+        }
+        fragment UserFields on User {
+            ... on User { name @include(if: $withName) }
+            email @skip(if: $withName)
+        }"#;
+
+        for (with_name, expected) in &[(true, "{me {name}}"), (false, "{me {email}}")] {
+            let mut variables = Variables::default();
+            variables.insert(Name::new("withName"), ConstValue::Boolean(*with_name));
+            let queries = PlanBuilder::new(&schema, parse_query(query).unwrap())
+                .variables(variables)
+                .preview()
+                .unwrap();
+            assert_eq!(queries[0].query, *expected);
+        }
+    }
+
+    #[test]
+    fn skip_and_include_combinations() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema("type Query { me: User } type User { id: ID! name: String! }").unwrap(),
+        )])
+        .unwrap();
+        let query = r#"query($skip: Boolean!, $include: Boolean!) {
+            me { id name @skip(if: $skip) @include(if: $include) }
+        }"#;
+
+        for (skip, include, expected) in &[
+            (false, false, "{me {id}}"),
+            (false, true, "{me {id name}}"),
+            (true, false, "{me {id}}"),
+            (true, true, "{me {id}}"),
+        ] {
+            let mut variables = Variables::default();
+            variables.insert(Name::new("skip"), ConstValue::Boolean(*skip));
+            variables.insert(Name::new("include"), ConstValue::Boolean(*include));
+            let queries = PlanBuilder::new(&schema, parse_query(query).unwrap())
+                .variables(variables)
+                .preview()
+                .unwrap();
+            assert_eq!(queries[0].query, *expected);
+        }
+    }
+
+    #[test]
+    fn batch_sibling_flatten_nodes() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { me: User topUsers: [User!]! }
+                    type User @key(fields: "id") { id: ID! username: String! }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "reviews".to_string(),
+                parse_schema(
+                    r#"extend type User @key(fields: "id") { id: ID! @external reviews: [String!]! reviewCount: Int! }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        let document = parse_query("{ me { reviews } topUsers { reviews } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+        let reviews = queries
+            .iter()
+            .filter(|query| query.service == "reviews")
+            .collect::<Vec<_>>();
+        assert_eq!(reviews.len(), 1);
+        assert!(reviews[0].path.as_deref().unwrap().contains(", "));
+
+        let document = parse_query("{ me { reviews } topUsers { reviewCount } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+        assert_eq!(
+            queries
+                .iter()
+                .filter(|query| query.service == "reviews")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn requires_fetched_from_owner_first() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "products".to_string(),
+                parse_schema(r#"type Product @key(fields: "upc") { upc: String! weight: Int! }"#)
+                    .unwrap(),
+            ),
+            (
+                "reviews".to_string(),
+                parse_schema(
+                    r#"type Query { topReviews: [Review!]! }
+                    type Review { body: String! product: Product! }
+                    extend type Product @key(fields: "upc") { upc: String! @external }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "inventory".to_string(),
+                parse_schema(
+                    r#"extend type Product @key(fields: "upc") {
+                        upc: String! @external
+                        weight: Int! @external
+                        shippingEstimate: Int! @requires(fields: "weight")
+                    }"#,
+                )
+                .unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        let document = parse_query("{ topReviews { product { shippingEstimate } } }").unwrap();
+        let queries = PlanBuilder::new(&schema, document).preview().unwrap();
+        assert_eq!(
+            queries
+                .iter()
+                .map(|query| query.service.as_str())
+                .collect::<Vec<_>>(),
+            vec!["reviews", "products", "inventory"]
+        );
+        assert!(!queries[0].query.contains("weight"));
+        assert!(queries[1]
+            .query
+            .contains("__graphgate_key2_upc:upc __graphgate_key2_weight:weight"));
+    }
+
+    #[test]
+    fn coerce_single_values_into_lists() {
+        let schema = ComposedSchema::parse(
+            "type Query { users(ids: [Int!]): [String!]! matrix(rows: [[Int]]): Int }",
+        )
+        .unwrap();
+        let preview = |query: &str, name: &str, value: Option<ConstValue>| {
+            let mut variables = Variables::default();
+            if let Some(value) = value {
+                variables.insert(Name::new(name), value);
+            }
+            PlanBuilder::new(&schema, parse_query(query).unwrap())
+                .variables(variables)
+                .preview()
+                .unwrap()
+                .remove(0)
+                .query
+        };
+
+        let query = "query($ids: [Int!]) { users(ids: $ids) }";
+        for (value, expected) in &[
+            (value!(1), value!([1])),
+            (value!([1, 2]), value!([1, 2])),
+            (value!(null), value!(null)),
+        ] {
+            assert_eq!(
+                preview(query, "ids", Some(value.clone())),
+                format!("{{users (ids: {})}}", expected)
+            );
+        }
+
+        let query = "query($rows: [[Int]]) { matrix(rows: $rows) }";
+        for (value, expected) in &[
+            (value!(1), value!([[1]])),
+            (value!([[1], null, 2]), value!([[1], null, [2]])),
+        ] {
+            assert_eq!(
+                preview(query, "rows", Some(value.clone())),
+                format!("{{matrix (rows: {})}}", expected)
+            );
+        }
+
+        let query = "query($ids: [Int!] = 3) { users(ids: $ids) }";
+        assert_eq!(preview(query, "ids", None), "{users (ids: [3])}");
+    }
+
+    #[test]
+    fn coalesce_aliased_fields() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                "type Query { user(id: ID!): User } type User { id: ID! name: String! email: String! }",
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+
+        let queries = PlanBuilder::new(
+            &schema,
+            parse_query(
+                "{ a: user(id: 1) { name } b: user(id: 1) { email } c: user(id: 2) { name } }",
+            )
+            .unwrap(),
+        )
+        .preview()
+        .unwrap();
+        assert_eq!(
+            queries[0].query,
+            "{a:user (id: 1) {name email} c:user (id: 2) {name}}"
+        );
+
+        let queries = PlanBuilder::new(
+            &schema,
+            parse_query("{ a: user(id: 1) { n: name } b: user(id: 1) { n: email } }").unwrap(),
+        )
+        .preview()
+        .unwrap();
+        assert_eq!(
+            queries[0].query,
+            "{a:user (id: 1) {n:name} b:user (id: 1) {n:email}}"
+        );
+    }
+
+    #[test]
+    fn omitted_enum_argument_defaults() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                r#"type Query { users(role: Role = ADMIN, limit: Int = 10): [String!]! }
+                enum Role { ADMIN USER }"#,
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+
+        let queries = PlanBuilder::new(&schema, parse_query("{ users }").unwrap())
+            .preview()
+            .unwrap();
+        assert_eq!(queries[0].query, "{users (role: ADMIN)}");
+
+        let queries = PlanBuilder::new(&schema, parse_query("{ users(role: USER) }").unwrap())
+            .preview()
+            .unwrap();
+        assert_eq!(queries[0].query, "{users (role: USER)}");
+    }
+
+    #[test]
+    fn deferred_root_fragments() {
+        let schema = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema("type Query { me: String }").unwrap(),
+            ),
+            (
+                "products".to_string(),
+                parse_schema("type Query { topProducts: [String!]! }").unwrap(),
+            ),
+        ])
+        .unwrap();
+
+        let document =
+            parse_query(r#"{ me ... @defer(label: "products") { topProducts } }"#).unwrap();
+        let defer = match PlanBuilder::new(&schema, document).plan().unwrap() {
+            PlanNode::Defer(defer) => defer,
+            node => panic!("expected a defer node, got {:?}", node),
+        };
+        assert!(matches!(&*defer.primary, PlanNode::Fetch(fetch) if fetch.query == "{me}"));
+        assert_eq!(defer.deferred.len(), 1);
+        assert_eq!(defer.deferred[0].label.as_deref(), Some("products"));
+        let nodes = match &defer.deferred[0].node {
+            PlanNode::Sequence(SequenceNode { nodes }) => nodes,
+            node => panic!("expected a sequence node, got {:?}", node),
+        };
+        assert!(matches!(
+            &nodes[0],
+            PlanNode::Fetch(fetch) if fetch.service == "products" && fetch.query == "{topProducts}"
+        ));
+        assert!(matches!(&nodes[1], PlanNode::NullCheck(_)));
+
+        let document = parse_query("{ me ... @defer(if: false) { topProducts } }").unwrap();
+        assert!(matches!(
+            PlanBuilder::new(&schema, document).plan().unwrap(),
+            PlanNode::Sequence(SequenceNode { nodes }) if matches!(&nodes[0], PlanNode::Parallel(_))
+        ));
+    }
+
+    #[test]
+    fn max_fragments() {
+        let schema = ComposedSchema::parse("type Query { a: Int b: Int }").unwrap();
+        let query = "{ ...A ...B } fragment A on Query { a } fragment B on Query { b }";
+
+        assert!(PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_fragments(2)
+            .plan()
+            .is_ok());
+        let response = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_fragments(1)
+            .plan()
+            .unwrap_err();
+        assert_eq!(
+            response.errors[0].message,
+            "Document defines 2 fragments, which exceeds the limit of 1."
+        );
+    }
+
+    #[test]
+    fn max_complexity() {
+        let schema = ComposedSchema::parse("type Query { a: A } type A { b: Int c: Int }").unwrap();
+        let query = "{ a { ...F } } fragment F on A { b c }";
+
+        assert!(PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_complexity(3)
+            .plan()
+            .is_ok());
+        let response = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_complexity(2)
+            .plan()
+            .unwrap_err();
+        assert_eq!(
+            response.errors[0].message,
+            "Operation has a cost of 3, which exceeds the budget of 2."
+        );
+    }
+
+    #[test]
+    fn complexity_with_fragment_cycles() {
+        let schema = ComposedSchema::parse("type Query { a: A } type A { a: A b: Int }").unwrap();
+        let query = "{ a { ...F } } fragment F on A { b a { ...G } } fragment G on A { ...F }";
+        let builder = PlanBuilder::new(&schema, parse_query(query).unwrap());
+        assert_eq!(builder.complexity(), Some(4));
+        assert!(builder.plan().is_err());
+
+        let mut query = "{ a { ...F40 } } fragment F0 on A { b }".to_string();
+        for i in 1..=40 {
+            query.push_str(&format!(
+                " fragment F{} on A {{ a {{ ...F{} ...F{} }} }}",
+                i,
+                i - 1,
+                i - 1
+            ));
+        }
+        let builder = PlanBuilder::new(&schema, parse_query(&query).unwrap());
+        assert_eq!(builder.complexity(), Some(1 << 41));
+    }
+
+    #[test]
+    fn max_root_fields() {
+        let schema = ComposedSchema::parse("type Query { a: Int b: Int }").unwrap();
+        let query = "{ a ...F x: a } fragment F on Query { b }";
+
+        assert!(PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_root_fields(3)
+            .plan()
+            .is_ok());
+        let response = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .max_root_fields(2)
+            .plan()
+            .unwrap_err();
+        assert_eq!(
+            response.errors[0].message,
+            "Operation has 3 root fields, which exceeds the limit of 2."
+        );
+    }
+
+    #[test]
+    fn operation_info_root_fields_from_nested_fragment() {
+        let schema = ComposedSchema::parse("type Query { viewer: Query me: String }").unwrap();
+        let query = "{ viewer { ...F } ...F } fragment F on Query { me }";
+        let info = PlanBuilder::new(&schema, parse_query(query).unwrap())
+            .operation_info()
+            .unwrap();
+        assert_eq!(
+            info.root_fields,
+            vec!["viewer".to_string(), "me".to_string()]
+        );
+    }
 }
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use spin::Mutex;
+
+use super::plan::PlanNode;
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct PlanCacheKey {
+    pub(crate) query: String,
+    pub(crate) operation_name: Option<String>,
+    pub(crate) variables: Vec<(String, String)>,
+    pub(crate) authorized: bool,
+    pub(crate) max_complexity: Option<usize>,
+}
+
+pub struct PlanCache {
+    capacity: usize,
+    plans: Mutex<IndexMap<PlanCacheKey, Arc<PlanNode<'static>>>>,
+}
+
+impl PlanCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            plans: Default::default(),
+        }
+    }
+
+    pub fn get(&self, key: &PlanCacheKey) -> Option<Arc<PlanNode<'static>>> {
+        let mut plans = self.plans.lock();
+        let (key, plan) = plans.shift_remove_entry(key)?;
+        plans.insert(key, plan.clone());
+        Some(plan)
+    }
+
+    pub fn insert(&self, key: PlanCacheKey, plan: PlanNode<'_>) -> Arc<PlanNode<'static>> {
+        let plan = Arc::new(plan.into_owned());
+        if self.capacity == 0 {
+            return plan;
+        }
+        let mut plans = self.plans.lock();
+        plans.shift_remove(&key);
+        if plans.len() >= self.capacity {
+            plans.shift_remove_index(0);
+        }
+        plans.insert(key, plan.clone());
+        plan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::{parse_query, parse_schema};
+
+    use super::*;
+    use crate::planner::PlanBuilder;
+    use crate::ComposedSchema;
+
+    fn key(query: &str) -> PlanCacheKey {
+        PlanCacheKey {
+            query: query.to_string(),
+            operation_name: None,
+            variables: Vec::new(),
+            authorized: false,
+            max_complexity: None,
+        }
+    }
+
+    #[test]
+    fn owned_plans_outlive_builder() {
+        let cache = PlanCache::new(1);
+        {
+            let schema = ComposedSchema::combine(vec![
+                (
+                    "accounts".to_string(),
+                    parse_schema("type Query { me: String }").unwrap(),
+                ),
+                (
+                    "products".to_string(),
+                    parse_schema("type Query { topProducts: [String!]! }").unwrap(),
+                ),
+            ])
+            .unwrap();
+            let builder = PlanBuilder::new(&schema, parse_query("{ me topProducts }").unwrap());
+            cache.insert(key("{ me topProducts }"), builder.plan().unwrap());
+        }
+
+        let plan = cache.get(&key("{ me topProducts }")).unwrap();
+        assert_eq!(
+            plan.services().into_iter().collect::<Vec<_>>(),
+            vec!["accounts", "products"]
+        );
+        assert!(cache.get(&key("{ me }")).is_none());
+    }
+
+    #[test]
+    fn compare_full_key() {
+        let schema = ComposedSchema::parse("type Query { a: Int }").unwrap();
+        let cache = PlanCache::new(4);
+        let builder = PlanBuilder::new(&schema, parse_query("{ a }").unwrap());
+        cache.insert(builder.cache_key("{ a }"), builder.plan().unwrap());
+
+        assert!(cache.get(&builder.cache_key("{ a }")).is_some());
+        assert!(cache.get(&builder.cache_key("{ a a }")).is_none());
+        let builder = PlanBuilder::new(&schema, parse_query("{ a }").unwrap()).authorized(true);
+        assert!(cache.get(&builder.cache_key("{ a }")).is_none());
+        assert!(cache
+            .get(&PlanCacheKey {
+                operation_name: Some("A".to_string()),
+                ..key("{ a }")
+            })
+            .is_none());
+    }
+
+    #[test]
+    fn evict_oldest_plan() {
+        let schema = ComposedSchema::parse("type Query { a: Int }").unwrap();
+        let cache = PlanCache::new(2);
+        for query in &["{ a }", "{ b: a }", "{ c: a }"] {
+            let builder = PlanBuilder::new(&schema, parse_query(query).unwrap());
+            cache.insert(key(query), builder.plan().unwrap());
+        }
+        assert!(cache.get(&key("{ a }")).is_none());
+        assert!(cache.get(&key("{ b: a }")).is_some());
+        assert!(cache.get(&key("{ c: a }")).is_some());
+
+        assert!(cache.get(&key("{ b: a }")).is_some());
+        let builder = PlanBuilder::new(&schema, parse_query("{ d: a }").unwrap());
+        cache.insert(key("{ d: a }"), builder.plan().unwrap());
+        assert!(cache.get(&key("{ b: a }")).is_some());
+        assert!(cache.get(&key("{ c: a }")).is_none());
+        assert!(cache.get(&key("{ d: a }")).is_some());
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::{HashMap, HashSet};
+
+use parser::types::{FragmentDefinition, Selection, SelectionSet};
+use parser::Positioned;
+use value::Name;
+
+pub(crate) fn compute_complexity(
+    fragments: &HashMap<Name, Positioned<FragmentDefinition>>,
+    selection_set: &SelectionSet,
+) -> usize {
+    let mut calculator = Calculator {
+        fragments,
+        costs: HashMap::new(),
+        visiting: HashSet::new(),
+    };
+    calculator.selection_set_cost(selection_set)
+}
+
+struct Calculator<'a> {
+    fragments: &'a HashMap<Name, Positioned<FragmentDefinition>>,
+    costs: HashMap<&'a str, usize>,
+    visiting: HashSet<&'a str>,
+}
+
+impl<'a> Calculator<'a> {
+    fn selection_set_cost(&mut self, selection_set: &'a SelectionSet) -> usize {
+        let mut cost = 0usize;
+        for selection in &selection_set.items {
+            let selection_cost = match &selection.node {
+                Selection::Field(field) => {
+                    1usize.saturating_add(self.selection_set_cost(&field.node.selection_set.node))
+                }
+                Selection::FragmentSpread(fragment_spread) => {
+                    self.fragment_cost(fragment_spread.node.fragment_name.node.as_str())
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    self.selection_set_cost(&inline_fragment.node.selection_set.node)
+                }
+            };
+            cost = cost.saturating_add(selection_cost);
+        }
+        cost
+    }
+
+    fn fragment_cost(&mut self, name: &'a str) -> usize {
+        if let Some(cost) = self.costs.get(name) {
+            return *cost;
+        }
+        let fragment = match self.fragments.get(name) {
+            Some(fragment) => fragment,
+            None => return 0,
+        };
+        if !self.visiting.insert(name) {
+            return 0;
+        }
+        let cost = self.selection_set_cost(&fragment.node.selection_set.node);
+        self.visiting.remove(name);
+        self.costs.insert(name, cost);
+        cost
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use value::{ConstValue, Name};
+
+type ResolverFn = dyn Fn(&BTreeMap<Name, ConstValue>) -> ConstValue + Send + Sync;
+
+#[derive(Clone)]
+pub struct ComputedFieldResolver(Arc<ResolverFn>);
+
+impl ComputedFieldResolver {
+    #[inline]
+    pub(crate) fn resolve(&self, object: &BTreeMap<Name, ConstValue>) -> ConstValue {
+        (self.0)(object)
+    }
+}
+
+impl Debug for ComputedFieldResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("ComputedFieldResolver")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ComputedFields(HashMap<String, HashMap<String, ComputedFieldResolver>>);
+
+impl ComputedFields {
+    pub fn add(
+        mut self,
+        type_name: impl Into<String>,
+        field_name: impl Into<String>,
+        resolver: impl Fn(&BTreeMap<Name, ConstValue>) -> ConstValue + Send + Sync + 'static,
+    ) -> Self {
+        self.0
+            .entry(type_name.into())
+            .or_default()
+            .insert(field_name.into(), ComputedFieldResolver(Arc::new(resolver)));
+        self
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, type_name: &str, field_name: &str) -> Option<&ComputedFieldResolver> {
+        self.0
+            .get(type_name)
+            .and_then(|fields| fields.get(field_name))
+    }
+}
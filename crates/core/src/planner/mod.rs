@@ -1,9 +1,24 @@
+mod advice;
 mod builder;
+mod cache;
+mod complexity;
+mod computed;
+mod operation_info;
 mod plan;
+mod preview;
+mod selection_width;
 mod types;
 
 pub use builder::PlanBuilder;
+pub use cache::{PlanCache, PlanCacheKey};
+pub use computed::{ComputedFieldResolver, ComputedFields};
+pub use operation_info::OperationInfo;
 pub use plan::{
-    FetchNode, FlattenNode, IntrospectionDirective, IntrospectionField, IntrospectionNode,
-    IntrospectionSelectionSet, ParallelNode, PathSegment, PlanNode, ResponsePath, SequenceNode,
+    AliasedField, BatchedPath, ComputeField, ComputeNode, DeferNode, DeferredNode, FetchNode,
+    FlattenNode, IntrospectionDirective, IntrospectionField, IntrospectionNode,
+    IntrospectionSelectionSet, NullCheckNode, ParallelNode, PathSegment, PlanNode, Projection,
+    ResponseField, ResponsePath, SequenceNode,
 };
+pub use preview::SubgraphQuery;
+
+pub(crate) use plan::{key_prefix, KEY_PREFIX};
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+use parser::types::{
+    ExecutableDocument, FragmentDefinition, OperationType, Selection, SelectionSet,
+};
+use parser::Positioned;
+use value::Name;
+
+use super::builder::find_operation;
+use super::plan::PlanNode;
+use crate::schema::{ComposedSchema, MetaType};
+
+#[derive(Debug, Clone)]
+pub struct OperationInfo {
+    pub ty: OperationType,
+    pub name: Option<String>,
+    pub root_fields: Vec<String>,
+    pub types: IndexSet<String>,
+    pub services: IndexSet<String>,
+}
+
+impl OperationInfo {
+    pub(crate) fn new(
+        schema: &ComposedSchema,
+        document: &ExecutableDocument,
+        operation_name: Option<&str>,
+    ) -> Option<Self> {
+        let (name, operation_definition) = find_operation(document, operation_name)?;
+        let root_type = match operation_definition.node.ty {
+            OperationType::Query => Some(schema.query_type()),
+            OperationType::Mutation => schema.mutation_type(),
+            OperationType::Subscription => schema.subscription_type(),
+        }
+        .and_then(|name| schema.types.get(name))?;
+
+        let mut info = OperationInfo {
+            ty: operation_definition.node.ty,
+            name: name.map(ToString::to_string),
+            root_fields: Vec::new(),
+            types: IndexSet::new(),
+            services: IndexSet::new(),
+        };
+        info.types.insert(root_type.name.to_string());
+
+        let mut collector = Collector {
+            schema,
+            fragments: &document.fragments,
+            visited_fragments: HashSet::new(),
+        };
+        collector.collect(
+            &mut info,
+            root_type,
+            &operation_definition.node.selection_set.node,
+            true,
+        );
+        Some(info)
+    }
+
+    pub fn set_plan(&mut self, plan: &PlanNode<'_>) {
+        self.services = plan
+            .services()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+    }
+}
+
+struct Collector<'a> {
+    schema: &'a ComposedSchema,
+    fragments: &'a HashMap<Name, Positioned<FragmentDefinition>>,
+    visited_fragments: HashSet<(&'a str, bool)>,
+}
+
+impl<'a> Collector<'a> {
+    fn collect(
+        &mut self,
+        info: &mut OperationInfo,
+        parent_type: &'a MetaType,
+        selection_set: &'a SelectionSet,
+        is_root: bool,
+    ) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => {
+                    let field_name = field.node.name.node.as_str();
+                    if is_root {
+                        info.root_fields.push(field_name.to_string());
+                    }
+                    let field_type =
+                        parent_type
+                            .field_by_name(field_name)
+                            .and_then(|field_definition| {
+                                self.schema.concrete_type_by_name(&field_definition.ty)
+                            });
+                    if let Some(field_type) = field_type {
+                        info.types.insert(field_type.name.to_string());
+                        self.collect(info, field_type, &field.node.selection_set.node, false);
+                    }
+                }
+                Selection::FragmentSpread(fragment_spread) => {
+                    let fragment_name = fragment_spread.node.fragment_name.node.as_str();
+                    if !self.visited_fragments.insert((fragment_name, is_root)) {
+                        continue;
+                    }
+                    if let Some(fragment) = self.fragments.get(fragment_name) {
+                        let ty = self
+                            .schema
+                            .types
+                            .get(fragment.node.type_condition.node.on.node.as_str());
+                        if let Some(ty) = ty {
+                            info.types.insert(ty.name.to_string());
+                            self.collect(info, ty, &fragment.node.selection_set.node, is_root);
+                        }
+                    }
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    let ty = match &inline_fragment.node.type_condition {
+                        Some(type_condition) => {
+                            match self.schema.types.get(type_condition.node.on.node.as_str()) {
+                                Some(ty) => ty,
+                                None => continue,
+                            }
+                        }
+                        None => parent_type,
+                    };
+                    info.types.insert(ty.name.to_string());
+                    self.collect(info, ty, &inline_fragment.node.selection_set.node, is_root);
+                }
+            }
+        }
+    }
+}
@@ -1,11 +1,21 @@
+use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::ops::{Deref, DerefMut};
+use std::time::Duration;
 
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
+use parser::types::Type;
 use value::Name;
 
+use super::computed::ComputedFieldResolver;
 use crate::schema::ConstValue;
 
+pub(crate) const KEY_PREFIX: &str = "__graphgate_key";
+
+pub(crate) fn key_prefix(prefix: usize) -> String {
+    format!("{}{}_", KEY_PREFIX, prefix)
+}
+
 #[derive(Debug)]
 pub enum PlanNode<'a> {
     Sequence(SequenceNode<'a>),
@@ -13,6 +23,9 @@ pub enum PlanNode<'a> {
     Introspection(IntrospectionNode),
     Fetch(FetchNode<'a>),
     Flatten(FlattenNode<'a>),
+    Compute(ComputeNode<'a>),
+    NullCheck(NullCheckNode<'a>),
+    Defer(DeferNode<'a>),
 }
 
 impl<'a> PlanNode<'a> {
@@ -23,13 +36,125 @@ impl<'a> PlanNode<'a> {
             _ => self,
         }
     }
+
+    pub fn is_deferred(&self) -> bool {
+        matches!(self, PlanNode::Defer(defer) if !defer.deferred.is_empty())
+    }
+
+    pub fn services(&self) -> IndexSet<&str> {
+        fn collect_services<'a>(services: &mut IndexSet<&'a str>, node: &'a PlanNode<'_>) {
+            match node {
+                PlanNode::Sequence(SequenceNode { nodes })
+                | PlanNode::Parallel(ParallelNode { nodes }) => nodes
+                    .iter()
+                    .for_each(|node| collect_services(services, node)),
+                PlanNode::Fetch(fetch) => {
+                    services.insert(&fetch.service);
+                }
+                PlanNode::Flatten(flatten) => {
+                    services.insert(&flatten.service);
+                }
+                PlanNode::Defer(defer) => {
+                    collect_services(services, &defer.primary);
+                    defer
+                        .deferred
+                        .iter()
+                        .for_each(|deferred| collect_services(services, &deferred.node));
+                }
+                PlanNode::Introspection(_) | PlanNode::Compute(_) | PlanNode::NullCheck(_) => {}
+            }
+        }
+
+        let mut services = IndexSet::new();
+        collect_services(&mut services, self);
+        services
+    }
+
+    pub fn into_owned(self) -> PlanNode<'static> {
+        match self {
+            PlanNode::Sequence(SequenceNode { nodes }) => PlanNode::Sequence(SequenceNode {
+                nodes: nodes.into_iter().map(PlanNode::into_owned).collect(),
+            }),
+            PlanNode::Parallel(ParallelNode { nodes }) => PlanNode::Parallel(ParallelNode {
+                nodes: nodes.into_iter().map(PlanNode::into_owned).collect(),
+            }),
+            PlanNode::Introspection(introspection) => PlanNode::Introspection(introspection),
+            PlanNode::Fetch(fetch) => PlanNode::Fetch(FetchNode {
+                service: owned(fetch.service),
+                query: fetch.query,
+                is_mutation: fetch.is_mutation,
+                response_keys: fetch.response_keys.into_iter().map(owned).collect(),
+                cache_ttl: fetch.cache_ttl,
+                aliases: fetch
+                    .aliases
+                    .into_iter()
+                    .map(|aliased| AliasedField {
+                        source: owned(aliased.source),
+                        response_key: owned(aliased.response_key),
+                        projection: aliased.projection.map(Projection::into_owned),
+                    })
+                    .collect(),
+            }),
+            PlanNode::Flatten(flatten) => PlanNode::Flatten(FlattenNode {
+                path: flatten.path.into_owned(),
+                prefix: flatten.prefix,
+                service: owned(flatten.service),
+                parent_type: owned(flatten.parent_type),
+                response_keys: flatten.response_keys.into_iter().map(owned).collect(),
+                required: flatten.required.into_iter().map(owned).collect(),
+                query: flatten.query,
+                batched: flatten
+                    .batched
+                    .into_iter()
+                    .map(|batched| BatchedPath {
+                        path: batched.path.into_owned(),
+                        prefix: batched.prefix,
+                    })
+                    .collect(),
+            }),
+            PlanNode::Compute(compute) => PlanNode::Compute(ComputeNode {
+                fields: compute
+                    .fields
+                    .into_iter()
+                    .map(|field| ComputeField {
+                        path: field.path.into_owned(),
+                        parent_type: owned(field.parent_type),
+                        response_key: owned(field.response_key),
+                        resolver: field.resolver.map(owned),
+                    })
+                    .collect(),
+            }),
+            PlanNode::NullCheck(null_check) => PlanNode::NullCheck(NullCheckNode {
+                fields: null_check
+                    .fields
+                    .into_iter()
+                    .map(ResponseField::into_owned)
+                    .collect(),
+            }),
+            PlanNode::Defer(defer) => PlanNode::Defer(DeferNode {
+                primary: Box::new(defer.primary.into_owned()),
+                deferred: defer
+                    .deferred
+                    .into_iter()
+                    .map(|deferred| DeferredNode {
+                        label: deferred.label.map(owned),
+                        node: deferred.node.into_owned(),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+fn owned<T: ToOwned + ?Sized + 'static>(value: Cow<'_, T>) -> Cow<'static, T> {
+    Cow::Owned(value.into_owned())
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct PathSegment<'a> {
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     pub is_list: bool,
-    pub possible_type: Option<&'a str>,
+    pub possible_type: Option<Cow<'a, str>>,
 }
 
 #[derive(Clone, Default, Hash, Eq, PartialEq)]
@@ -46,7 +171,7 @@ impl<'a> Debug for ResponsePath<'a> {
             } else {
                 write!(f, "{}", segment.name)?;
             }
-            if let Some(possible_type) = segment.possible_type {
+            if let Some(possible_type) = &segment.possible_type {
                 write!(f, "({})", possible_type)?;
             }
         }
@@ -60,6 +185,21 @@ impl<'a> Display for ResponsePath<'a> {
     }
 }
 
+impl<'a> ResponsePath<'a> {
+    fn into_owned(self) -> ResponsePath<'static> {
+        ResponsePath(
+            self.0
+                .into_iter()
+                .map(|segment| PathSegment {
+                    name: owned(segment.name),
+                    is_list: segment.is_list,
+                    possible_type: segment.possible_type.map(owned),
+                })
+                .collect(),
+        )
+    }
+}
+
 impl<'a> Deref for ResponsePath<'a> {
     type Target = Vec<PathSegment<'a>>;
 
@@ -104,20 +244,152 @@ pub struct IntrospectionSelectionSet(pub Vec<IntrospectionField>);
 
 #[derive(Debug)]
 pub struct IntrospectionNode {
+    pub root_type: Name,
     pub selection_set: IntrospectionSelectionSet,
 }
 
+#[derive(Debug, Default)]
+pub struct Projection<'a> {
+    pub fields: Vec<(Cow<'a, str>, Option<Projection<'a>>)>,
+    pub key_prefixes: Vec<usize>,
+}
+
+impl<'a> Projection<'a> {
+    pub(crate) fn add_field(&mut self, response_key: &'a str, projection: Option<Projection<'a>>) {
+        match self.fields.iter_mut().find(|(key, _)| *key == response_key) {
+            Some((_, Some(current))) => {
+                if let Some(projection) = projection {
+                    current.merge(projection);
+                }
+            }
+            Some((_, None)) => {}
+            None => self.fields.push((Cow::Borrowed(response_key), projection)),
+        }
+    }
+
+    fn merge(&mut self, other: Projection<'a>) {
+        for (key, projection) in other.fields {
+            match self.fields.iter_mut().find(|(current, _)| *current == key) {
+                Some((_, Some(current))) => {
+                    if let Some(projection) = projection {
+                        current.merge(projection);
+                    }
+                }
+                Some((_, None)) => {}
+                None => self.fields.push((key, projection)),
+            }
+        }
+        self.key_prefixes.extend(other.key_prefixes);
+    }
+
+    fn into_owned(self) -> Projection<'static> {
+        Projection {
+            fields: self
+                .fields
+                .into_iter()
+                .map(|(key, projection)| (owned(key), projection.map(Projection::into_owned)))
+                .collect(),
+            key_prefixes: self.key_prefixes,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AliasedField<'a> {
+    pub source: Cow<'a, str>,
+    pub response_key: Cow<'a, str>,
+    pub projection: Option<Projection<'a>>,
+}
+
 #[derive(Debug)]
 pub struct FetchNode<'a> {
-    pub service: &'a str,
+    pub service: Cow<'a, str>,
     pub query: String,
+    pub is_mutation: bool,
+    pub response_keys: Vec<Cow<'a, str>>,
+    pub aliases: Vec<AliasedField<'a>>,
+    pub cache_ttl: Option<Duration>,
 }
 
 #[derive(Debug)]
 pub struct FlattenNode<'a> {
     pub path: ResponsePath<'a>,
     pub prefix: usize,
-    pub service: &'a str,
-    pub parent_type: &'a str,
+    pub service: Cow<'a, str>,
+    pub parent_type: Cow<'a, str>,
+    pub response_keys: Vec<Cow<'a, str>>,
+    pub required: Vec<Cow<'a, str>>,
     pub query: String,
+    pub batched: Vec<BatchedPath<'a>>,
+}
+
+impl<'a> FlattenNode<'a> {
+    pub fn paths(&self) -> impl Iterator<Item = (&ResponsePath<'a>, usize)> {
+        std::iter::once((&self.path, self.prefix)).chain(
+            self.batched
+                .iter()
+                .map(|batched| (&batched.path, batched.prefix)),
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchedPath<'a> {
+    pub path: ResponsePath<'a>,
+    pub prefix: usize,
+}
+
+#[derive(Debug)]
+pub struct ComputeField<'a> {
+    pub path: ResponsePath<'a>,
+    pub parent_type: Cow<'a, str>,
+    pub response_key: Cow<'a, str>,
+    pub resolver: Option<Cow<'a, ComputedFieldResolver>>,
+}
+
+#[derive(Debug)]
+pub struct ComputeNode<'a> {
+    pub fields: Vec<ComputeField<'a>>,
+}
+
+#[derive(Debug)]
+pub struct ResponseField<'a> {
+    pub parent_type: Cow<'a, str>,
+    pub field_name: Cow<'a, str>,
+    pub response_key: Cow<'a, str>,
+    pub ty: Cow<'a, Type>,
+    pub selection_set: Vec<ResponseField<'a>>,
+}
+
+impl<'a> ResponseField<'a> {
+    fn into_owned(self) -> ResponseField<'static> {
+        ResponseField {
+            parent_type: owned(self.parent_type),
+            field_name: owned(self.field_name),
+            response_key: owned(self.response_key),
+            ty: owned(self.ty),
+            selection_set: self
+                .selection_set
+                .into_iter()
+                .map(ResponseField::into_owned)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NullCheckNode<'a> {
+    pub fields: Vec<ResponseField<'a>>,
+}
+
+#[derive(Debug)]
+pub struct DeferredNode<'a> {
+    pub label: Option<Cow<'a, str>>,
+    pub node: PlanNode<'a>,
+}
+
+#[derive(Debug)]
+pub struct DeferNode<'a> {
+    pub primary: Box<PlanNode<'a>>,
+    pub deferred: Vec<DeferredNode<'a>>,
 }
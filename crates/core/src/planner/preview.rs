@@ -0,0 +1,61 @@
+use serde::Serialize;
+
+use super::plan::PlanNode;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphQuery {
+    pub service: String,
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub representation_type: Option<String>,
+}
+
+impl<'a> PlanNode<'a> {
+    pub fn subgraph_queries(&self) -> Vec<SubgraphQuery> {
+        fn collect_queries(queries: &mut Vec<SubgraphQuery>, node: &PlanNode<'_>) {
+            match node {
+                PlanNode::Sequence(sequence) => sequence
+                    .nodes
+                    .iter()
+                    .for_each(|node| collect_queries(queries, node)),
+                PlanNode::Parallel(parallel) => parallel
+                    .nodes
+                    .iter()
+                    .for_each(|node| collect_queries(queries, node)),
+                PlanNode::Fetch(fetch) => queries.push(SubgraphQuery {
+                    service: fetch.service.to_string(),
+                    query: fetch.query.clone(),
+                    path: None,
+                    representation_type: None,
+                }),
+                PlanNode::Flatten(flatten) => queries.push(SubgraphQuery {
+                    service: flatten.service.to_string(),
+                    query: flatten.query.clone(),
+                    path: Some(
+                        flatten
+                            .paths()
+                            .map(|(path, _)| path.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ),
+                    representation_type: Some(flatten.parent_type.to_string()),
+                }),
+                PlanNode::Defer(defer) => {
+                    collect_queries(queries, &defer.primary);
+                    defer
+                        .deferred
+                        .iter()
+                        .for_each(|deferred| collect_queries(queries, &deferred.node));
+                }
+                PlanNode::Introspection(_) | PlanNode::Compute(_) | PlanNode::NullCheck(_) => {}
+            }
+        }
+
+        let mut queries = Vec::new();
+        collect_queries(&mut queries, self);
+        queries
+    }
+}
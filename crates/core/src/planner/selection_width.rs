@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use parser::types::{Field, FragmentDefinition, Selection, SelectionSet};
+use parser::{Pos, Positioned};
+use value::Name;
+
+use crate::ServerError;
+
+pub(crate) fn check_selection_width(
+    fragments: &HashMap<Name, Positioned<FragmentDefinition>>,
+    selection_set: &Positioned<SelectionSet>,
+    max_width: usize,
+) -> Option<ServerError> {
+    let mut checker = WidthChecker {
+        fragments,
+        max_width,
+        path: Vec::new(),
+    };
+    checker.check(&selection_set.node, selection_set.pos)
+}
+
+struct WidthChecker<'a> {
+    fragments: &'a HashMap<Name, Positioned<FragmentDefinition>>,
+    max_width: usize,
+    path: Vec<&'a str>,
+}
+
+impl<'a> WidthChecker<'a> {
+    fn check(&mut self, selection_set: &'a SelectionSet, pos: Pos) -> Option<ServerError> {
+        let mut fields = Vec::new();
+        self.collect_fields(&mut fields, selection_set);
+
+        if fields.len() > self.max_width {
+            let location = if self.path.is_empty() {
+                "the operation root".to_string()
+            } else {
+                format!("\"{}\"", self.path.join("."))
+            };
+            return Some(ServerError {
+                message: format!(
+                    "Selection set at {} has {} fields, which exceeds the limit of {}.",
+                    location,
+                    fields.len(),
+                    self.max_width
+                ),
+                locations: vec![pos],
+                path: Default::default(),
+                extensions: Default::default(),
+            });
+        }
+
+        for field in fields {
+            self.path.push(field.node.response_key().node.as_str());
+            let err = self.check(&field.node.selection_set.node, field.pos);
+            self.path.pop();
+            if err.is_some() {
+                return err;
+            }
+        }
+        None
+    }
+
+    fn collect_fields(
+        &self,
+        fields: &mut Vec<&'a Positioned<Field>>,
+        selection_set: &'a SelectionSet,
+    ) {
+        for selection in &selection_set.items {
+            match &selection.node {
+                Selection::Field(field) => fields.push(field),
+                Selection::FragmentSpread(fragment_spread) => {
+                    if let Some(fragment) = self
+                        .fragments
+                        .get(fragment_spread.node.fragment_name.node.as_str())
+                    {
+                        self.collect_fields(fields, &fragment.node.selection_set.node);
+                    }
+                }
+                Selection::InlineFragment(inline_fragment) => {
+                    self.collect_fields(fields, &inline_fragment.node.selection_set.node);
+                }
+            }
+        }
+    }
+}
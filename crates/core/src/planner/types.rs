@@ -1,18 +1,30 @@
+use std::borrow::Cow;
 use std::fmt::{Result as FmtResult, Write};
 
 use indexmap::IndexMap;
 use parser::types::{Directive, Field};
 use parser::Positioned;
-use value::{Name, Value, Variables};
+use value::{ConstValue, Name, Value, Variables};
 
-use super::plan::ResponsePath;
+use super::plan::{key_prefix, AliasedField, Projection, ResponsePath};
 use crate::schema::{KeyFields, MetaType};
 
 pub struct FieldRef<'a> {
     pub field: &'a Field,
+    pub defaults: Vec<(&'a Name, &'a ConstValue)>,
     pub selection_set: SelectionRefSet<'a>,
 }
 
+impl<'a> FieldRef<'a> {
+    fn projection(&self) -> Option<Projection<'a>> {
+        if self.selection_set.0.is_empty() {
+            None
+        } else {
+            Some(self.selection_set.projection())
+        }
+    }
+}
+
 pub struct RequiredRef<'a> {
     pub prefix: usize,
     pub fields: &'a KeyFields,
@@ -38,12 +50,175 @@ impl<'a> SelectionRefSet<'a> {
         stringify_selection_ref_set_rec(&mut s, variables, self).unwrap();
         s
     }
+
+    pub fn response_keys(&self) -> Vec<Cow<'a, str>> {
+        let mut response_keys = Vec::new();
+        for selection in &self.0 {
+            match selection {
+                SelectionRef::FieldRef(field_ref) => {
+                    let response_key = field_ref.field.response_key().node.as_str();
+                    if !response_keys.contains(&Cow::Borrowed(response_key)) {
+                        response_keys.push(Cow::Borrowed(response_key));
+                    }
+                }
+                SelectionRef::InlineFragment { selection_set, .. } => {
+                    for response_key in selection_set.response_keys() {
+                        if !response_keys.contains(&response_key) {
+                            response_keys.push(response_key);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        response_keys
+    }
+
+    pub fn coalesce_aliases(self, variables: &Variables) -> (Self, Vec<AliasedField<'a>>) {
+        let mut selections = Vec::new();
+        let mut signatures: Vec<Option<String>> = Vec::new();
+        let mut groups: IndexMap<usize, Vec<AliasedField<'a>>> = IndexMap::new();
+
+        for selection in self.0 {
+            let field_ref = match selection {
+                SelectionRef::FieldRef(field_ref) => field_ref,
+                selection => {
+                    selections.push(selection);
+                    signatures.push(None);
+                    continue;
+                }
+            };
+            let response_key = field_ref.field.response_key().node.as_str();
+            let signature = field_signature(&field_ref, variables);
+            let target = signatures.iter().enumerate().position(|(idx, current)| {
+                if current.as_deref() != Some(signature.as_str()) {
+                    return false;
+                }
+                let target = match &selections[idx] {
+                    SelectionRef::FieldRef(target) => target,
+                    _ => return false,
+                };
+                let taken = match groups.get(&idx) {
+                    Some(members) => members
+                        .iter()
+                        .any(|member| member.response_key == response_key),
+                    None => target.field.response_key().node.as_str() == response_key,
+                };
+                !taken
+                    && !target
+                        .selection_set
+                        .conflicts_with(&field_ref.selection_set, variables)
+            });
+
+            match target {
+                Some(idx) => {
+                    if let SelectionRef::FieldRef(target) = &mut selections[idx] {
+                        let source = target.field.response_key().node.as_str();
+                        let members = groups.entry(idx).or_default();
+                        if members.is_empty() {
+                            members.push(AliasedField {
+                                source: Cow::Borrowed(source),
+                                response_key: Cow::Borrowed(source),
+                                projection: target.projection(),
+                            });
+                        }
+                        members.push(AliasedField {
+                            source: Cow::Borrowed(source),
+                            response_key: Cow::Borrowed(response_key),
+                            projection: field_ref.projection(),
+                        });
+                        target.selection_set.0.extend(field_ref.selection_set.0);
+                    }
+                }
+                None => {
+                    selections.push(SelectionRef::FieldRef(field_ref));
+                    signatures.push(Some(signature));
+                }
+            }
+        }
+
+        (
+            SelectionRefSet(selections),
+            groups
+                .into_iter()
+                .flat_map(|(_, members)| members)
+                .collect(),
+        )
+    }
+
+    fn projection(&self) -> Projection<'a> {
+        fn collect<'a>(projection: &mut Projection<'a>, selection_set: &SelectionRefSet<'a>) {
+            for selection in &selection_set.0 {
+                match selection {
+                    SelectionRef::FieldRef(field_ref) => projection.add_field(
+                        field_ref.field.response_key().node.as_str(),
+                        field_ref.projection(),
+                    ),
+                    SelectionRef::IntrospectionTypename => projection.add_field("__typename", None),
+                    SelectionRef::RequiredRef(required_ref) => {
+                        projection.key_prefixes.push(required_ref.prefix)
+                    }
+                    SelectionRef::InlineFragment { selection_set, .. } => {
+                        collect(projection, selection_set)
+                    }
+                }
+            }
+        }
+
+        let mut projection = Projection::default();
+        collect(&mut projection, self);
+        projection
+    }
+
+    fn conflicts_with(&self, other: &SelectionRefSet<'a>, variables: &Variables) -> bool {
+        fn collect_fields<'a, 'b>(
+            fields: &mut Vec<&'b FieldRef<'a>>,
+            selection_set: &'b SelectionRefSet<'a>,
+        ) {
+            for selection in &selection_set.0 {
+                match selection {
+                    SelectionRef::FieldRef(field_ref) => fields.push(field_ref),
+                    SelectionRef::InlineFragment { selection_set, .. } => {
+                        collect_fields(fields, selection_set)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut fields = Vec::new();
+        collect_fields(&mut fields, self);
+        let mut other_fields = Vec::new();
+        collect_fields(&mut other_fields, other);
+
+        other_fields.iter().any(|b| {
+            fields.iter().any(|a| {
+                a.field.response_key().node == b.field.response_key().node
+                    && (field_signature(a, variables) != field_signature(b, variables)
+                        || a.selection_set.conflicts_with(&b.selection_set, variables))
+            })
+        })
+    }
+}
+
+fn field_signature(field_ref: &FieldRef<'_>, variables: &Variables) -> String {
+    let field = field_ref.field;
+    let mut s = String::new();
+    s.push_str(field.name.node.as_str());
+    if !field.arguments.is_empty() || !field_ref.defaults.is_empty() {
+        stringify_argument(&mut s, variables, &field.arguments, &field_ref.defaults).unwrap();
+    }
+    if !field.directives.is_empty() {
+        stringify_directives(&mut s, variables, &field.directives).unwrap();
+    }
+    s
 }
 
 fn stringify_argument(
     w: &mut String,
     variables: &Variables,
     arguments: &[(Positioned<Name>, Positioned<Value>)],
+    defaults: &[(&Name, &ConstValue)],
 ) -> FmtResult {
     write!(w, "(")?;
     for (idx, (name, value)) in arguments.iter().enumerate() {
@@ -63,13 +238,19 @@ fn stringify_argument(
             }
         }
     }
+    for (idx, (name, value)) in defaults.iter().enumerate() {
+        if idx > 0 || !arguments.is_empty() {
+            write!(w, " ")?;
+        }
+        write!(w, "{}: {}", name, value)?;
+    }
     write!(w, ")")
 }
 
 fn stringify_directive(w: &mut String, variables: &Variables, directive: &Directive) -> FmtResult {
     write!(w, "@{}", directive.name.node.as_str())?;
     if !directive.arguments.is_empty() {
-        stringify_argument(w, variables, &directive.arguments)?;
+        stringify_argument(w, variables, &directive.arguments, &[])?;
     }
     Ok(())
 }
@@ -97,9 +278,9 @@ fn stringify_key_fields(w: &mut String, prefix: usize, fields: &KeyFields) -> Fm
         for (idx, (field_name, children)) in fields.iter().enumerate() {
             if idx > 0 {
                 write!(w, " ")?;
-                write!(w, "{}", field_name)?;
-                stringify_key_fields_no_prefix(w, children)?;
             }
+            write!(w, "{}", field_name)?;
+            stringify_key_fields_no_prefix(w, children)?;
         }
         write!(w, "}}")
     }
@@ -108,7 +289,7 @@ fn stringify_key_fields(w: &mut String, prefix: usize, fields: &KeyFields) -> Fm
         if idx > 0 {
             write!(w, " ")?;
         }
-        write!(w, "__key{}_{}:{}", prefix, field_name, field_name)?;
+        write!(w, "{}{}:{}", key_prefix(prefix), field_name, field_name)?;
         stringify_key_fields_no_prefix(w, &children)?;
     }
     Ok(())
@@ -131,9 +312,9 @@ fn stringify_selection_ref_set_rec(
                     write!(w, "{}:", alias.node)?;
                 }
                 write!(w, "{}", field.field.name.node)?;
-                if !field.field.arguments.is_empty() {
+                if !field.field.arguments.is_empty() || !field.defaults.is_empty() {
                     write!(w, " ")?;
-                    stringify_argument(w, variables, &field.field.arguments)?;
+                    stringify_argument(w, variables, &field.field.arguments, &field.defaults)?;
                 }
                 if !field.field.directives.is_empty() {
                     write!(w, " ")?;
@@ -150,11 +331,12 @@ fn stringify_selection_ref_set_rec(
             SelectionRef::RequiredRef(require_ref) => {
                 write!(
                     w,
-                    " ... {{ __key{}___typename:__typename ",
-                    require_ref.prefix,
+                    " ... {{ {}__typename:__typename ",
+                    key_prefix(require_ref.prefix),
                 )?;
                 stringify_key_fields(w, require_ref.prefix, &require_ref.fields)?;
                 if let Some(requires) = require_ref.requires {
+                    write!(w, " ")?;
                     stringify_key_fields(w, require_ref.prefix, &requires)?;
                 }
                 write!(w, " }} ")?;
@@ -180,6 +362,38 @@ pub struct FetchEntity<'a> {
     pub parent_type: &'a MetaType,
     pub prefix: usize,
     pub fields: Vec<&'a Field>,
+    pub required: Vec<&'a str>,
+    pub dependencies: Vec<RequiredRef<'a>>,
+    pub dependents: FetchEntityGroup<'a>,
+}
+
+impl<'a> FetchEntity<'a> {
+    pub fn new(parent_type: &'a MetaType, prefix: usize) -> Self {
+        Self {
+            parent_type,
+            prefix,
+            fields: Vec::new(),
+            required: Vec::new(),
+            dependencies: Vec::new(),
+            dependents: Default::default(),
+        }
+    }
+
+    pub fn require(mut self, keys: &'a KeyFields, requires: Option<&'a KeyFields>) -> Self {
+        self.add_required(keys, requires);
+        self
+    }
+
+    pub fn add_required(&mut self, keys: &'a KeyFields, requires: Option<&'a KeyFields>) {
+        for name in keys
+            .keys()
+            .chain(requires.into_iter().flat_map(|requires| requires.keys()))
+        {
+            if !self.required.contains(&name.as_str()) {
+                self.required.push(name.as_str());
+            }
+        }
+    }
 }
 
 #[derive(Clone, Eq, PartialEq, Hash)]
@@ -10,8 +10,9 @@ use parser::types::{
 use parser::{Positioned, Result};
 use value::{ConstValue, Name};
 
+use super::link::resolve_link_imports;
 use super::type_ext::TypeExt;
-use super::CombineError;
+use super::{CombineError, CompositionError};
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Deprecation {
@@ -45,6 +46,9 @@ pub struct MetaField {
     pub service: Option<String>,
     pub requires: Option<KeyFields>,
     pub provides: Option<KeyFields>,
+    pub masked: bool,
+    pub inaccessible: bool,
+    pub cache_max_age: Option<u64>,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -163,6 +167,7 @@ pub struct MetaDirective {
     pub description: Option<String>,
     pub locations: Vec<DirectiveLocation>,
     pub arguments: IndexMap<Name, MetaInputValue>,
+    pub is_repeatable: bool,
 }
 
 #[derive(Debug, Default)]
@@ -194,7 +199,12 @@ impl ComposedSchema {
                         convert_type_definition(type_definition.node),
                     );
                 }
-                TypeSystemDefinition::Directive(_) => {}
+                TypeSystemDefinition::Directive(directive_definition) => {
+                    composed_schema.directives.insert(
+                        directive_definition.node.name.node.clone(),
+                        convert_directive_definition(directive_definition.node, true),
+                    );
+                }
             }
         }
 
@@ -204,8 +214,9 @@ impl ComposedSchema {
 
     pub fn combine(
         federation_sdl: impl IntoIterator<Item = (String, ServiceDocument)>,
-    ) -> ::std::result::Result<Self, CombineError> {
+    ) -> ::std::result::Result<Self, CompositionError> {
         let mut composed_schema = ComposedSchema::default();
+        let mut errors = Vec::new();
         let root_objects = &["Query", "Mutation"];
 
         for obj in root_objects {
@@ -232,7 +243,7 @@ impl ComposedSchema {
         composed_schema.mutation_type = Some(Name::new("Mutation"));
 
         for (service, doc) in federation_sdl {
-            for definition in doc.definitions {
+            for definition in resolve_link_imports(doc).definitions {
                 match definition {
                     TypeSystemDefinition::Type(type_definition) => {
                         if let types::TypeKind::Object(ObjectType { implements, fields }) =
@@ -244,6 +255,8 @@ impl ComposedSchema {
                                 .description
                                 .map(|description| description.node);
                             let is_extend = type_definition.node.extend;
+                            let is_root_type =
+                                matches!(name.as_str(), "Query" | "Mutation" | "Subscription");
                             let meta_type = composed_schema
                                 .types
                                 .entry(name.clone())
@@ -261,6 +274,7 @@ impl ComposedSchema {
                                     input_fields: Default::default(),
                                 });
 
+                            let previous_owner = meta_type.owner.clone();
                             if !is_extend {
                                 meta_type.owner = Some(service.clone());
                             };
@@ -290,6 +304,8 @@ impl ComposedSchema {
                                 .extend(implements.into_iter().map(|implement| implement.node));
 
                             for field in fields {
+                                let is_inaccessible =
+                                    has_directive(&field.node.directives, "inaccessible");
                                 if is_extend {
                                     let is_external =
                                         has_directive(&field.node.directives, "external");
@@ -298,14 +314,30 @@ impl ComposedSchema {
                                     }
                                 }
 
-                                if meta_type.fields.contains_key(&field.node.name.node) {
-                                    return Err(CombineError::FieldConflicted {
+                                if let Some(existing) = meta_type
+                                    .fields
+                                    .get(&field.node.name.node)
+                                    .filter(|existing| !existing.inaccessible)
+                                {
+                                    if is_inaccessible {
+                                        continue;
+                                    }
+                                    let mut services = existing
+                                        .service
+                                        .clone()
+                                        .or_else(|| previous_owner.clone())
+                                        .into_iter()
+                                        .collect::<Vec<_>>();
+                                    services.push(service.clone());
+                                    errors.push(CombineError::FieldConflicted {
                                         type_name: type_definition.node.name.node.to_string(),
                                         field_name: field.node.name.node.to_string(),
+                                        services,
                                     });
+                                    continue;
                                 }
                                 let mut meta_field = convert_field_definition(field.node);
-                                if is_extend {
+                                if is_extend || is_root_type {
                                     meta_field.service = Some(service.clone());
                                 }
                                 meta_type.fields.insert(meta_field.name.clone(), meta_field);
@@ -314,9 +346,11 @@ impl ComposedSchema {
                             let meta_type = convert_type_definition(type_definition.node);
                             if let Some(meta_type2) = composed_schema.types.get(&meta_type.name) {
                                 if meta_type2 != &meta_type {
-                                    return Err(CombineError::DefinitionConflicted {
+                                    errors.push(CombineError::DefinitionConflicted {
                                         type_name: meta_type.name.to_string(),
+                                        service: service.clone(),
                                     });
+                                    continue;
                                 }
                             }
                             composed_schema
@@ -325,9 +359,16 @@ impl ComposedSchema {
                         }
                     }
                     TypeSystemDefinition::Schema(_schema_definition) => {
-                        return Err(CombineError::SchemaIsNotAllowed)
+                        errors.push(CombineError::SchemaIsNotAllowed {
+                            service: service.clone(),
+                        });
+                    }
+                    TypeSystemDefinition::Directive(directive_definition) => {
+                        composed_schema.directives.insert(
+                            directive_definition.node.name.node.clone(),
+                            convert_directive_definition(directive_definition.node, true),
+                        );
                     }
-                    TypeSystemDefinition::Directive(_directive_definition) => {}
                 }
             }
         }
@@ -339,6 +380,11 @@ impl ComposedSchema {
             }
         }
 
+        check_key_fields(&mut errors, &composed_schema);
+        check_enum_defaults(&mut errors, &mut composed_schema);
+        if !errors.is_empty() {
+            return Err(CompositionError { errors });
+        }
         finish_schema(&mut composed_schema);
         Ok(composed_schema)
     }
@@ -556,6 +602,9 @@ fn convert_field_definition(definition: types::FieldDefinition) -> MetaField {
         service: None,
         requires: None,
         provides: None,
+        masked: false,
+        inaccessible: false,
+        cache_max_age: None,
     };
 
     for directive in definition.directives {
@@ -575,6 +624,15 @@ fn convert_field_definition(definition: types::FieldDefinition) -> MetaField {
                     field_definition.provides = parse_fields(fields.node).map(convert_key_fields);
                 }
             }
+            "authzMask" => field_definition.masked = true,
+            "inaccessible" => field_definition.inaccessible = true,
+            "cacheControl" => {
+                if let Some(ConstValue::Number(max_age)) =
+                    get_argument(&directive.node.arguments, "maxAge").map(|value| &value.node)
+                {
+                    field_definition.cache_max_age = max_age.as_u64();
+                }
+            }
             _ => {}
         }
     }
@@ -610,7 +668,12 @@ fn convert_input_value_definition(arg: parser::types::InputValueDefinition) -> M
     }
 }
 
-fn convert_directive_definition(directive_definition: DirectiveDefinition) -> MetaDirective {
+// The parser does not read the `repeatable` keyword, so directives declared in SDL are treated
+// as repeatable and only the built-in directives are checked for repetition.
+fn convert_directive_definition(
+    directive_definition: DirectiveDefinition,
+    is_repeatable: bool,
+) -> MetaDirective {
     MetaDirective {
         name: directive_definition.name.node,
         description: directive_definition
@@ -631,6 +694,7 @@ fn convert_directive_definition(directive_definition: DirectiveDefinition) -> Me
                 )
             })
             .collect(),
+        is_repeatable,
     }
 }
 
@@ -651,6 +715,102 @@ fn has_directive(directives: &[Positioned<ConstDirective>], name: &str) -> bool
         .any(|directive| directive.node.name.node.as_str() == name)
 }
 
+fn check_key_fields(errors: &mut Vec<CombineError>, composed_schema: &ComposedSchema) {
+    fn check_key_fields_rec(
+        errors: &mut Vec<CombineError>,
+        composed_schema: &ComposedSchema,
+        service: &str,
+        ty: &MetaType,
+        key_fields: &KeyFields,
+    ) {
+        for (field_name, children) in key_fields.iter() {
+            let field = match ty.fields.get(field_name) {
+                Some(field) => field,
+                None => {
+                    errors.push(CombineError::KeyFieldNotFound {
+                        type_name: ty.name.to_string(),
+                        field_name: field_name.to_string(),
+                        service: service.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if !children.is_empty() {
+                if let Some(field_type) = composed_schema.concrete_type_by_name(&field.ty) {
+                    check_key_fields_rec(errors, composed_schema, service, field_type, children);
+                }
+            }
+        }
+    }
+
+    for ty in composed_schema.types.values() {
+        for (service, keys) in &ty.keys {
+            for key_fields in keys {
+                check_key_fields_rec(errors, composed_schema, service, ty, key_fields);
+            }
+        }
+    }
+}
+
+fn check_enum_defaults(errors: &mut Vec<CombineError>, composed_schema: &mut ComposedSchema) {
+    fn coerce_enum_value(enum_type: &MetaType, value: &ConstValue) -> Option<ConstValue> {
+        match value {
+            ConstValue::Null => Some(ConstValue::Null),
+            ConstValue::Enum(name) if enum_type.enum_values.contains_key(name) => {
+                Some(ConstValue::Enum(name.clone()))
+            }
+            ConstValue::String(name) if enum_type.enum_values.contains_key(name.as_str()) => {
+                Some(ConstValue::Enum(Name::new(name)))
+            }
+            ConstValue::List(items) => items
+                .iter()
+                .map(|item| coerce_enum_value(enum_type, item))
+                .collect::<Option<Vec<_>>>()
+                .map(ConstValue::List),
+            _ => None,
+        }
+    }
+
+    let mut coerced = Vec::new();
+    for ty in composed_schema.types.values() {
+        for field in ty.fields.values() {
+            for arg in field.arguments.values() {
+                let default_value = match &arg.default_value {
+                    Some(default_value) => default_value,
+                    None => continue,
+                };
+                let enum_type = match composed_schema.concrete_type_by_name(&arg.ty) {
+                    Some(enum_type) if enum_type.kind == TypeKind::Enum => enum_type,
+                    _ => continue,
+                };
+                match coerce_enum_value(enum_type, default_value) {
+                    Some(value) => {
+                        coerced.push((ty.name.clone(), field.name.clone(), arg.name.clone(), value))
+                    }
+                    None => errors.push(CombineError::InvalidEnumDefault {
+                        type_name: ty.name.to_string(),
+                        field_name: field.name.to_string(),
+                        argument: arg.name.to_string(),
+                        enum_name: enum_type.name.to_string(),
+                        value: default_value.to_string(),
+                    }),
+                }
+            }
+        }
+    }
+
+    for (type_name, field_name, arg_name, value) in coerced {
+        if let Some(arg) = composed_schema
+            .types
+            .get_mut(&type_name)
+            .and_then(|ty| ty.fields.get_mut(&field_name))
+            .and_then(|field| field.arguments.get_mut(&arg_name))
+        {
+            arg.default_value = Some(value);
+        }
+    }
+}
+
 fn finish_schema(composed_schema: &mut ComposedSchema) {
     for definition in parser::parse_schema(include_str!("builtin.graphql"))
         .unwrap()
@@ -668,7 +828,7 @@ fn finish_schema(composed_schema: &mut ComposedSchema) {
             TypeSystemDefinition::Directive(directive_definition) => {
                 composed_schema.directives.insert(
                     directive_definition.node.name.node.clone(),
-                    convert_directive_definition(directive_definition.node),
+                    convert_directive_definition(directive_definition.node, false),
                 );
             }
             TypeSystemDefinition::Schema(_) => {}
@@ -707,6 +867,9 @@ fn finish_schema(composed_schema: &mut ComposedSchema) {
                 service: None,
                 requires: None,
                 provides: None,
+                masked: false,
+                inaccessible: false,
+                cache_max_age: None,
             },
         );
 
@@ -722,6 +885,9 @@ fn finish_schema(composed_schema: &mut ComposedSchema) {
                 service: None,
                 requires: None,
                 provides: None,
+                masked: false,
+                inaccessible: false,
+                cache_max_age: None,
             },
         );
     }
@@ -743,3 +909,89 @@ fn finish_schema(composed_schema: &mut ComposedSchema) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_schema;
+
+    use super::*;
+
+    #[test]
+    fn composition_errors() {
+        let errors = ComposedSchema::combine(vec![
+            (
+                "accounts".to_string(),
+                parse_schema(
+                    r#"type Query { me: User }
+                    type User @key(fields: "uid") { id: ID! }"#,
+                )
+                .unwrap(),
+            ),
+            (
+                "products".to_string(),
+                parse_schema("type Query { me: User }").unwrap(),
+            ),
+        ])
+        .unwrap_err()
+        .errors;
+
+        assert_eq!(
+            errors,
+            vec![
+                CombineError::FieldConflicted {
+                    type_name: "Query".to_string(),
+                    field_name: "me".to_string(),
+                    services: vec!["accounts".to_string(), "products".to_string()],
+                },
+                CombineError::KeyFieldNotFound {
+                    type_name: "User".to_string(),
+                    field_name: "uid".to_string(),
+                    service: "accounts".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_argument_defaults() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                r#"type Query { users(role: Role = ADMIN, roles: [Role!] = ["USER"]): [String!]! }
+                enum Role { ADMIN USER }"#,
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+        let users = &schema.types["Query"].fields["users"];
+        assert_eq!(
+            users.arguments["role"].default_value,
+            Some(ConstValue::Enum(Name::new("ADMIN")))
+        );
+        assert_eq!(
+            users.arguments["roles"].default_value,
+            Some(ConstValue::List(vec![ConstValue::Enum(Name::new("USER"))]))
+        );
+
+        let errors = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                r#"type Query { users(role: Role = OWNER): [String!]! }
+                enum Role { ADMIN USER }"#,
+            )
+            .unwrap(),
+        )])
+        .unwrap_err()
+        .errors;
+        assert_eq!(
+            errors,
+            vec![CombineError::InvalidEnumDefault {
+                type_name: "Query".to_string(),
+                field_name: "users".to_string(),
+                argument: "role".to_string(),
+                enum_name: "Role".to_string(),
+                value: "OWNER".to_string(),
+            }]
+        );
+    }
+}
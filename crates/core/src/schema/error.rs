@@ -1,16 +1,52 @@
+use std::fmt::{self, Display, Formatter};
+
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Eq, PartialEq)]
 pub enum CombineError {
-    #[error("Redefining the schema is not allowed.")]
-    SchemaIsNotAllowed,
+    #[error("Redefining the schema is not allowed in service '{service}'.")]
+    SchemaIsNotAllowed { service: String },
 
-    #[error("Type '{type_name}' definition conflicted.")]
-    DefinitionConflicted { type_name: String },
+    #[error("Type '{type_name}' definition in service '{service}' conflicted.")]
+    DefinitionConflicted { type_name: String, service: String },
 
-    #[error("Field '{type_name}.{field_name}' definition conflicted.")]
+    #[error("Field '{type_name}.{field_name}' definition conflicted between services '{}'.", .services.join("', '"))]
     FieldConflicted {
         type_name: String,
         field_name: String,
+        services: Vec<String>,
+    },
+
+    #[error("Key field '{type_name}.{field_name}' used by service '{service}' is not defined.")]
+    KeyFieldNotFound {
+        type_name: String,
+        field_name: String,
+        service: String,
     },
+
+    #[error("Default value '{value}' of argument '{type_name}.{field_name}({argument})' is not a value of enum '{enum_name}'.")]
+    InvalidEnumDefault {
+        type_name: String,
+        field_name: String,
+        argument: String,
+        enum_name: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Error)]
+pub struct CompositionError {
+    pub errors: Vec<CombineError>,
+}
+
+impl Display for CompositionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (idx, error) in self.errors.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", error)?;
+        }
+        Ok(())
+    }
 }
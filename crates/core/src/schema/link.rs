@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use parser::types::{
+    ConstDirective, InterfaceType, ObjectType, SchemaDefinition, ServiceDocument, TypeKind,
+    TypeSystemDefinition,
+};
+use parser::Positioned;
+use value::{ConstValue, Name};
+
+const FEDERATION_SPEC: &str = "specs.apollo.dev/federation";
+
+#[derive(Default)]
+struct LinkImports {
+    namespace: Option<String>,
+    aliases: HashMap<String, String>,
+}
+
+impl LinkImports {
+    fn collect(&mut self, directives: &[Positioned<ConstDirective>]) {
+        for directive in directives {
+            if directive.node.name.node.as_str() != "link" {
+                continue;
+            }
+            let is_federation = matches!(
+                get_argument(&directive.node.arguments, "url"),
+                Some(ConstValue::String(url)) if url.contains(FEDERATION_SPEC)
+            );
+            if !is_federation {
+                continue;
+            }
+
+            self.namespace = Some(match get_argument(&directive.node.arguments, "as") {
+                Some(ConstValue::String(namespace)) => namespace.clone(),
+                _ => "federation".to_string(),
+            });
+
+            if let Some(ConstValue::List(imports)) =
+                get_argument(&directive.node.arguments, "import")
+            {
+                for import in imports {
+                    let import = match import {
+                        ConstValue::Object(import) => import,
+                        _ => continue,
+                    };
+                    if let (Some(ConstValue::String(name)), Some(ConstValue::String(alias))) =
+                        (import.get("name"), import.get("as"))
+                    {
+                        self.aliases.insert(
+                            alias.trim_start_matches('@').to_string(),
+                            name.trim_start_matches('@').to_string(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, name: &str) -> Option<Name> {
+        if let Some(name) = self.aliases.get(name) {
+            return Some(Name::new(name));
+        }
+        let namespace = self.namespace.as_deref()?;
+        name.strip_prefix(namespace)
+            .and_then(|name| name.strip_prefix("__"))
+            .map(Name::new)
+    }
+
+    fn rename(&self, directives: &mut [Positioned<ConstDirective>]) {
+        for directive in directives {
+            if let Some(name) = self.resolve(directive.node.name.node.as_str()) {
+                directive.node.name.node = name;
+            }
+        }
+    }
+}
+
+pub(crate) fn resolve_link_imports(mut document: ServiceDocument) -> ServiceDocument {
+    let mut imports = LinkImports::default();
+    for definition in &document.definitions {
+        if let TypeSystemDefinition::Schema(schema) = definition {
+            imports.collect(&schema.node.directives);
+        }
+    }
+    if imports.namespace.is_none() {
+        return document;
+    }
+
+    document.definitions.retain(|definition| match definition {
+        TypeSystemDefinition::Schema(schema) => !is_link_only(&schema.node),
+        _ => true,
+    });
+
+    for definition in &mut document.definitions {
+        if let TypeSystemDefinition::Type(type_definition) = definition {
+            imports.rename(&mut type_definition.node.directives);
+            match &mut type_definition.node.kind {
+                TypeKind::Object(ObjectType { fields, .. })
+                | TypeKind::Interface(InterfaceType { fields, .. }) => {
+                    for field in fields {
+                        imports.rename(&mut field.node.directives);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    document
+}
+
+fn is_link_only(schema_definition: &SchemaDefinition) -> bool {
+    schema_definition.query.is_none()
+        && schema_definition.mutation.is_none()
+        && schema_definition.subscription.is_none()
+        && schema_definition
+            .directives
+            .iter()
+            .all(|directive| directive.node.name.node.as_str() == "link")
+}
+
+fn get_argument<'a>(
+    arguments: &'a [(Positioned<Name>, Positioned<ConstValue>)],
+    name: &str,
+) -> Option<&'a ConstValue> {
+    arguments
+        .iter()
+        .find(|(arg_name, _)| arg_name.node.as_str() == name)
+        .map(|(_, value)| &value.node)
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_schema;
+
+    use crate::ComposedSchema;
+
+    #[test]
+    fn link_imports() {
+        let document = parse_schema(
+            r#"extend schema @link(url: "https://specs.apollo.dev/federation/v2.0", import: [{ name: "@key", as: "@primaryKey" }])
+            type Query { me: User product: Product }
+            type User @primaryKey(fields: "id") { id: ID! }
+            type Product @federation__key(fields: "upc") { upc: String! }"#,
+        )
+        .unwrap();
+        let schema = ComposedSchema::combine(vec![("accounts".to_string(), document)]).unwrap();
+
+        assert!(schema.types["User"].keys.contains_key("accounts"));
+        assert!(schema.types["Product"].keys.contains_key("accounts"));
+    }
+}
@@ -1,11 +1,12 @@
 mod composed_schema;
 mod error;
+mod link;
 mod type_ext;
 
 pub use composed_schema::{
     ComposedSchema, Deprecation, KeyFields, MetaEnumValue, MetaField, MetaInputValue, MetaType,
     TypeKind,
 };
-pub use error::CombineError;
+pub use error::{CombineError, CompositionError};
 pub use type_ext::TypeExt;
 pub use value::ConstValue;
@@ -45,10 +45,23 @@ pub fn check_rules(
         ProvidedNonNullArguments,
         ScalarLeafs,
         UniqueArgumentNames,
+        UniqueDirectivesPerLocation,
         UniqueVariableNames,
+        VariableValuesOfCorrectType,
         VariablesAreInputTypes,
         VariableInAllowedPosition
     );
     visit(&mut visitor, &mut ctx, &document);
     ctx.errors
 }
+
+pub fn check_warnings(
+    composed_schema: &ComposedSchema,
+    document: &ExecutableDocument,
+    variables: &Variables,
+) -> Vec<RuleError> {
+    let mut ctx = VisitorContext::new(composed_schema, document, variables);
+    let mut visitor = rules!(FloatPrecision);
+    visit(&mut visitor, &mut ctx, &document);
+    ctx.errors
+}
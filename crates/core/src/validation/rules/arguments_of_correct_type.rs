@@ -129,6 +129,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn int_boundary_values() {
+        expect_passes_rule!(
+            factory,
+            r#"
+            {
+              complicatedArgs {
+                max: intArgField(intArg: 2147483647)
+                min: intArgField(intArg: -2147483648)
+              }
+            }
+        "#,
+        );
+
+        let variables = value::Variables::default();
+        let doc = parser::parse_query(
+            r#"
+            {
+              complicatedArgs {
+                above: intArgField(intArg: 2147483648)
+                below: intArgField(intArg: -2147483649)
+              }
+            }
+        "#,
+        )
+        .unwrap();
+        let errors = crate::validation::test_harness::validate(&doc, &variables, factory)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| err.message)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                "Invalid value for argument \"intArg\", Int cannot represent non 32-bit signed integer value 2147483648",
+                "Invalid value for argument \"intArg\", Int cannot represent non 32-bit signed integer value -2147483649",
+            ]
+        );
+    }
+
     #[test]
     fn good_boolean_value() {
         expect_passes_rule!(
@@ -563,6 +603,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enum_value_suggestions() {
+        let variables = value::Variables::default();
+        let doc = parser::parse_query(
+            r#"
+            {
+              dog {
+                sit: doesKnowCommand(dogCommand: sit)
+                heal: doesKnowCommand(dogCommand: HEAL)
+              }
+            }
+        "#,
+        )
+        .unwrap();
+        let errors = crate::validation::test_harness::validate(&doc, &variables, factory)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| err.message)
+            .collect::<Vec<_>>();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].ends_with(". Did you mean \"SIT\"?"));
+        assert!(errors[1].ends_with(". Did you mean \"HEEL\"?"));
+    }
+
     #[test]
     fn good_list_value() {
         expect_passes_rule!(
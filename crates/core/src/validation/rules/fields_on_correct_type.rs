@@ -17,7 +17,11 @@ impl<'a> Visitor<'a> for FieldsOnCorrectType {
                 return;
             }
 
-            if !parent_type.fields.contains_key(&field.node.name.node) {
+            let is_accessible = parent_type
+                .field_by_name(&field.node.name.node)
+                .map(|field_definition| !field_definition.inaccessible)
+                .unwrap_or_default();
+            if !is_accessible {
                 ctx.report_error(
                     vec![field.pos],
                     format!(
@@ -26,7 +30,11 @@ impl<'a> Visitor<'a> for FieldsOnCorrectType {
                         parent_type.name,
                         make_suggestion(
                             " Did you mean",
-                            parent_type.fields.keys().map(|name| name.as_str()),
+                            parent_type
+                                .fields
+                                .values()
+                                .filter(|field_definition| !field_definition.inaccessible)
+                                .map(|field_definition| field_definition.name.as_str()),
                             &field.node.name.node,
                         )
                         .unwrap_or_default()
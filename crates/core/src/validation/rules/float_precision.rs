@@ -0,0 +1,133 @@
+use indexmap::IndexMap;
+use parser::types::{Directive, Field};
+use parser::Positioned;
+use value::{Name, Value};
+
+use crate::schema::MetaInputValue;
+use crate::validation::utils::{float_precision_loss, PathNode};
+use crate::validation::{Visitor, VisitorContext};
+
+#[derive(Default)]
+pub struct FloatPrecision<'a> {
+    current_args: Option<&'a IndexMap<Name, MetaInputValue>>,
+}
+
+impl<'a> Visitor<'a> for FloatPrecision<'a> {
+    fn enter_directive(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        directive: &'a Positioned<Directive>,
+    ) {
+        self.current_args = ctx
+            .schema
+            .directives
+            .get(directive.node.name.node.as_str())
+            .map(|d| &d.arguments);
+    }
+
+    fn exit_directive(
+        &mut self,
+        _ctx: &mut VisitorContext<'a>,
+        _directive: &'a Positioned<Directive>,
+    ) {
+        self.current_args = None;
+    }
+
+    fn enter_argument(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        name: &'a Positioned<Name>,
+        value: &'a Positioned<Value>,
+    ) {
+        if let Some(arg) = self
+            .current_args
+            .and_then(|args| args.get(name.node.as_str()))
+        {
+            let value = value
+                .node
+                .clone()
+                .into_const_with(|var_name| {
+                    ctx.variables.get(&var_name).map(Clone::clone).ok_or(())
+                })
+                .ok();
+
+            if let Some(reason) = value.and_then(|value| {
+                float_precision_loss(
+                    ctx.schema,
+                    &arg.ty,
+                    &value,
+                    PathNode::new(arg.name.as_str()),
+                )
+            }) {
+                ctx.report_error(
+                    vec![name.pos],
+                    format!("Imprecise value for argument {}", reason),
+                );
+            }
+        }
+    }
+
+    fn enter_field(&mut self, ctx: &mut VisitorContext<'a>, field: &'a Positioned<Field>) {
+        self.current_args = ctx
+            .parent_type()
+            .and_then(|p| p.field_by_name(&field.node.name.node))
+            .map(|f| &f.arguments);
+    }
+
+    fn exit_field(&mut self, _ctx: &mut VisitorContext<'a>, _field: &'a Positioned<Field>) {
+        self.current_args = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub fn factory<'a>() -> FloatPrecision<'a> {
+        FloatPrecision::default()
+    }
+
+    #[test]
+    fn exact_floats() {
+        expect_passes_rule!(
+            factory,
+            r#"
+            {
+              complicatedArgs {
+                a: floatArgField(floatArg: 1.5)
+                b: floatArgField(floatArg: 9007199254740992)
+                c: floatArgField(floatArg: -9007199254740992)
+              }
+            }
+        "#,
+        );
+    }
+
+    #[test]
+    fn large_integer_into_float() {
+        expect_fails_rule!(
+            factory,
+            r#"
+            {
+              complicatedArgs {
+                floatArgField(floatArg: 9007199254740993)
+              }
+            }
+        "#,
+        );
+    }
+
+    #[test]
+    fn large_integer_into_id() {
+        expect_passes_rule!(
+            factory,
+            r#"
+            {
+              complicatedArgs {
+                idArgField(idArg: 9007199254740993)
+              }
+            }
+        "#,
+        );
+    }
+}
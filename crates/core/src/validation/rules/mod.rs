@@ -1,6 +1,7 @@
 mod arguments_of_correct_type;
 mod default_values_of_correct_type;
 mod fields_on_correct_type;
+mod float_precision;
 mod fragments_on_composite_types;
 mod known_argument_names;
 mod known_directives;
@@ -15,13 +16,16 @@ mod possible_fragment_spreads;
 mod provided_non_null_arguments;
 mod scalar_leafs;
 mod unique_argument_names;
+mod unique_directives_per_location;
 mod unique_variable_names;
+mod variable_values_of_correct_type;
 mod variables_are_input_types;
 mod variables_in_allowed_position;
 
 pub use arguments_of_correct_type::ArgumentsOfCorrectType;
 pub use default_values_of_correct_type::DefaultValuesOfCorrectType;
 pub use fields_on_correct_type::FieldsOnCorrectType;
+pub use float_precision::FloatPrecision;
 pub use fragments_on_composite_types::FragmentsOnCompositeTypes;
 pub use known_argument_names::KnownArgumentNames;
 pub use known_directives::KnownDirectives;
@@ -36,6 +40,8 @@ pub use possible_fragment_spreads::PossibleFragmentSpreads;
 pub use provided_non_null_arguments::ProvidedNonNullArguments;
 pub use scalar_leafs::ScalarLeafs;
 pub use unique_argument_names::UniqueArgumentNames;
+pub use unique_directives_per_location::UniqueDirectivesPerLocation;
 pub use unique_variable_names::UniqueVariableNames;
+pub use variable_values_of_correct_type::VariableValuesOfCorrectType;
 pub use variables_are_input_types::VariablesAreInputTypes;
 pub use variables_in_allowed_position::VariableInAllowedPosition;
@@ -27,14 +27,14 @@ impl<'a> CycleDetector<'a> {
             let index = self.path_indices.get(name).cloned();
 
             if let Some(index) = index {
-                let err_pos = if index < path.len() {
-                    path[index].1
-                } else {
-                    *pos
-                };
+                let mut locations = path[index..]
+                    .iter()
+                    .map(|(_, pos)| *pos)
+                    .collect::<Vec<_>>();
+                locations.push(*pos);
 
                 self.errors.push(RuleError {
-                    locations: vec![err_pos],
+                    locations,
                     message: format!("Cannot spread fragment \"{}\"", name),
                 });
             } else if !self.visited.contains(name) {
@@ -323,4 +323,21 @@ mod tests {
         "#,
         );
     }
+
+    #[test]
+    fn cycle_reports_every_spread() {
+        let variables = value::Variables::default();
+        let doc = parser::parse_query(
+            r#"
+          fragment fragA on Dog { ...fragB }
+          fragment fragB on Dog { ...fragA }
+          { __typename }
+        "#,
+        )
+        .unwrap();
+        let errors =
+            crate::validation::test_harness::validate(&doc, &variables, factory).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].locations.len(), 2);
+    }
 }
@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+
+use parser::types::{
+    Directive, Field, FragmentDefinition, FragmentSpread, InlineFragment, OperationDefinition,
+};
+use parser::Positioned;
+use value::Name;
+
+use crate::validation::{Visitor, VisitorContext};
+
+#[derive(Default)]
+pub struct UniqueDirectivesPerLocation;
+
+fn check_directives(ctx: &mut VisitorContext<'_>, directives: &[Positioned<Directive>]) {
+    let mut names = HashSet::new();
+    for directive in directives {
+        let name = directive.node.name.node.as_str();
+        let is_repeatable = ctx
+            .schema
+            .directives
+            .get(name)
+            .map(|directive| directive.is_repeatable)
+            .unwrap_or(true);
+        if !is_repeatable && !names.insert(name) {
+            ctx.report_error(
+                vec![directive.pos],
+                format!(
+                    "The directive \"@{}\" can only be used once at this location.",
+                    name
+                ),
+            );
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for UniqueDirectivesPerLocation {
+    fn enter_operation_definition(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        _name: Option<&'a Name>,
+        operation_definition: &'a Positioned<OperationDefinition>,
+    ) {
+        check_directives(ctx, &operation_definition.node.directives);
+    }
+
+    fn enter_fragment_definition(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        _name: &'a Name,
+        fragment_definition: &'a Positioned<FragmentDefinition>,
+    ) {
+        check_directives(ctx, &fragment_definition.node.directives);
+    }
+
+    fn enter_field(&mut self, ctx: &mut VisitorContext<'a>, field: &'a Positioned<Field>) {
+        check_directives(ctx, &field.node.directives);
+    }
+
+    fn enter_fragment_spread(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        fragment_spread: &'a Positioned<FragmentSpread>,
+    ) {
+        check_directives(ctx, &fragment_spread.node.directives);
+    }
+
+    fn enter_inline_fragment(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        inline_fragment: &'a Positioned<InlineFragment>,
+    ) {
+        check_directives(ctx, &inline_fragment.node.directives);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::{parse_query, parse_schema};
+    use value::Variables;
+
+    use super::*;
+    use crate::validation::visitor::visit;
+    use crate::ComposedSchema;
+
+    pub fn factory() -> UniqueDirectivesPerLocation {
+        UniqueDirectivesPerLocation
+    }
+
+    #[test]
+    fn no_directives() {
+        expect_passes_rule!(
+            factory,
+            r#"
+          {
+            dog {
+              name
+            }
+          }
+        "#,
+        );
+    }
+
+    #[test]
+    fn same_directive_in_different_locations() {
+        expect_passes_rule!(
+            factory,
+            r#"
+          {
+            dog @skip(if: false) {
+              name @skip(if: false)
+              ... on Dog @skip(if: false) {
+                nickname
+              }
+            }
+          }
+        "#,
+        );
+    }
+
+    #[test]
+    fn different_directives_in_one_location() {
+        expect_passes_rule!(
+            factory,
+            r#"
+          {
+            dog @skip(if: false) @include(if: true) {
+              name
+            }
+          }
+        "#,
+        );
+    }
+
+    #[test]
+    fn duplicate_directives_in_one_location() {
+        expect_fails_rule!(
+            factory,
+            r#"
+          {
+            dog @skip(if: false) @skip(if: true) {
+              name
+            }
+          }
+        "#,
+        );
+    }
+
+    #[test]
+    fn duplicate_directives_on_fragment_spread() {
+        expect_fails_rule!(
+            factory,
+            r#"
+          {
+            dog {
+              ...DogName @include(if: true) @include(if: false)
+            }
+          }
+          fragment DogName on Dog {
+            name
+          }
+        "#,
+        );
+    }
+
+    #[test]
+    fn repeatable_directive() {
+        let schema = ComposedSchema::combine(vec![(
+            "accounts".to_string(),
+            parse_schema(
+                r#"directive @tag(name: String!) on FIELD
+                type Query { a: Int }"#,
+            )
+            .unwrap(),
+        )])
+        .unwrap();
+        assert!(schema.directives["tag"].is_repeatable);
+        assert!(!schema.directives["skip"].is_repeatable);
+        let variables = Variables::default();
+
+        let document =
+            parse_query(r#"{ a @tag(name: "x") @tag(name: "y") @skip(if: false) }"#).unwrap();
+        let mut ctx = VisitorContext::new(&schema, &document, &variables);
+        visit(&mut factory(), &mut ctx, &document);
+        assert!(ctx.errors.is_empty());
+
+        let document =
+            parse_query(r#"{ a @tag(name: "x") @skip(if: false) @skip(if: true) }"#).unwrap();
+        let mut ctx = VisitorContext::new(&schema, &document, &variables);
+        visit(&mut factory(), &mut ctx, &document);
+        assert_eq!(ctx.errors.len(), 1);
+    }
+}
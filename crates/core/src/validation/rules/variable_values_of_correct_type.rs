@@ -0,0 +1,154 @@
+use parser::types::VariableDefinition;
+use parser::Positioned;
+
+use crate::validation::utils::{is_valid_input_value, PathNode};
+use crate::validation::{Visitor, VisitorContext};
+
+#[derive(Default)]
+pub struct VariableValuesOfCorrectType;
+
+impl<'a> Visitor<'a> for VariableValuesOfCorrectType {
+    fn enter_variable_definition(
+        &mut self,
+        ctx: &mut VisitorContext<'a>,
+        variable_definition: &'a Positioned<VariableDefinition>,
+    ) {
+        let name = &variable_definition.node.name.node;
+        if let Some(value) = ctx.variables.get(name) {
+            if let Some(reason) = is_valid_input_value(
+                ctx.schema,
+                &variable_definition.node.var_type.node,
+                value,
+                PathNode::new(name),
+            ) {
+                ctx.report_error(
+                    vec![variable_definition.pos],
+                    format!("Invalid value for variable {}", reason),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use value::{value, ConstValue, Name, Variables};
+
+    use super::*;
+
+    pub fn factory() -> VariableValuesOfCorrectType {
+        VariableValuesOfCorrectType
+    }
+
+    fn errors(query: &str, value: ConstValue) -> Vec<String> {
+        let mut variables = Variables::default();
+        variables.insert(Name::new("a"), value);
+        let doc = parser::parse_query(query).unwrap();
+        match crate::validation::test_harness::validate(&doc, &variables, factory) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|err| err.message).collect(),
+        }
+    }
+
+    #[test]
+    fn non_null_list_of_non_null() {
+        let query = "query($a: [Int!]!) { dog { name } }";
+        assert!(errors(query, value!([1, 2])).is_empty());
+        assert_eq!(
+            errors(query, value!([1, null])),
+            vec!["Invalid value for variable \"a.1\", expected type \"Int!\"".to_string()]
+        );
+        assert_eq!(
+            errors(query, value!(null)),
+            vec!["Invalid value for variable \"a\", expected type \"[Int!]!\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn nested_lists() {
+        let query = "query($a: [[Int]]) { dog { name } }";
+        assert!(errors(query, value!([[1], [2, null], null])).is_empty());
+        assert_eq!(
+            errors(query, value!([[1], ["x"]])),
+            vec!["Invalid value for variable \"a.1.0\", expected type \"Int\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn single_value_to_list_coercion() {
+        assert!(errors("query($a: [Int!]!) { dog { name } }", value!(1)).is_empty());
+        assert!(errors("query($a: [[Int]]) { dog { name } }", value!(1)).is_empty());
+        assert_eq!(
+            errors("query($a: [Int!]!) { dog { name } }", value!("x")),
+            vec!["Invalid value for variable \"a\", expected type \"Int\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn integers_out_of_range() {
+        assert_eq!(
+            errors(
+                "query($a: Int) { dog { name } }",
+                ConstValue::Number(u64::MAX.into())
+            ),
+            vec![format!(
+                "Invalid value for variable \"a\", Int cannot represent non 32-bit signed integer value {}",
+                u64::MAX
+            )]
+        );
+        assert_eq!(
+            errors("query($a: [Int]) { dog { name } }", value!([1, 1e20])),
+            vec!["Invalid value for variable \"a.1\", Int cannot represent non 32-bit signed integer value 1e20".to_string()]
+        );
+        assert!(errors(
+            "query($a: ID) { dog { name } }",
+            ConstValue::Number(u64::MAX.into())
+        )
+        .is_empty());
+        assert_eq!(
+            errors("query($a: ID) { dog { name } }", value!(1e20)),
+            vec!["Invalid value for variable \"a\", ID cannot represent out-of-range integer value 1e20".to_string()]
+        );
+        assert_eq!(
+            errors("query($a: Int) { dog { name } }", value!(1.5)),
+            vec!["Invalid value for variable \"a\", expected type \"Int\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn input_object_errors_are_stable() {
+        for _ in 0..16 {
+            assert_eq!(
+                errors(
+                    "query($a: ComplexInput) { dog { name } }",
+                    value!({ "requiredField": true, "alpha": 1, "omega": 2, "zeta": 3 })
+                ),
+                vec![
+                    "Invalid value for variable \"a\", unknown field \"alpha\" of type \"ComplexInput\""
+                        .to_string()
+                ]
+            );
+            assert_eq!(
+                errors(
+                    "query($a: MultipleRequiredInput) { dog { name } }",
+                    value!({ "third": 3, "zeta": 1 })
+                ),
+                vec!["Invalid value for variable \"a\", field \"first\" of type \"MultipleRequiredInput\" is required but not provided".to_string()]
+            );
+            assert_eq!(
+                errors(
+                    "query($a: MultipleRequiredInput) { dog { name } }",
+                    value!({ "first": 1 })
+                ),
+                vec!["Invalid value for variable \"a\", field \"second\" of type \"MultipleRequiredInput\" is required but not provided".to_string()]
+            );
+        }
+    }
+
+    #[test]
+    fn variable_not_provided() {
+        let doc = parser::parse_query("query($a: [Int!]!) { dog { name } }").unwrap();
+        let variables = Variables::default();
+        assert!(crate::validation::test_harness::validate(&doc, &variables, factory).is_ok());
+    }
+}
@@ -1,10 +1,10 @@
-use std::collections::HashSet;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 use parser::types::{BaseType, Type};
-use value::{ConstValue, Value};
+use value::{ConstValue, Number, Value};
 
 use crate::schema::TypeKind;
+use crate::validation::suggestion::make_suggestion;
 use crate::ComposedSchema;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -89,6 +89,16 @@ fn valid_error(path_node: &PathNode, msg: String) -> String {
     format!("\"{}\", {}", path_node, msg)
 }
 
+fn enum_value_suggestion<'a>(values: impl Iterator<Item = &'a str> + Clone, input: &str) -> String {
+    match values
+        .clone()
+        .find(|value| value.eq_ignore_ascii_case(input))
+    {
+        Some(value) => format!(". Did you mean \"{}\"?", value),
+        None => make_suggestion(". Did you mean", values, input).unwrap_or_default(),
+    }
+}
+
 pub fn is_valid_input_value(
     schema: &ComposedSchema,
     ty: &Type,
@@ -118,7 +128,22 @@ pub fn is_valid_input_value(
                 if let Some(ty) = schema.types.get(type_name) {
                     match ty.kind {
                         TypeKind::Scalar => {
-                            if is_valid_scalar_value(ty.name.as_str(), value) {
+                            if let (Some(n), "Int") = (int_out_of_range(value), ty.name.as_str()) {
+                                Some(valid_error(
+                                    &path_node,
+                                    format!(
+                                        "Int cannot represent non 32-bit signed integer value {}",
+                                        n
+                                    ),
+                                ))
+                            } else if let (Some(n), "ID") =
+                                (id_out_of_range(value), ty.name.as_str())
+                            {
+                                Some(valid_error(
+                                    &path_node,
+                                    format!("ID cannot represent out-of-range integer value {}", n),
+                                ))
+                            } else if is_valid_scalar_value(ty.name.as_str(), value) {
                                 None
                             } else {
                                 Some(valid_error(
@@ -133,8 +158,13 @@ pub fn is_valid_input_value(
                                     Some(valid_error(
                                         &path_node,
                                         format!(
-                                            "enumeration type \"{}\" does not contain the value \"{}\"",
-                                            ty.name, value
+                                            "enumeration type \"{}\" does not contain the value \"{}\"{}",
+                                            ty.name,
+                                            value,
+                                            enum_value_suggestion(
+                                                ty.enum_values.keys().map(|name| name.as_str()),
+                                                value
+                                            )
                                         ),
                                     ))
                                 } else {
@@ -149,10 +179,7 @@ pub fn is_valid_input_value(
                         }
                         TypeKind::InputObject => {
                             if let ConstValue::Object(values) = value {
-                                let mut input_names = values.keys().collect::<HashSet<_>>();
-
                                 for field in ty.input_fields.values() {
-                                    input_names.remove(&field.name);
                                     if let Some(value) = values.get(&field.name) {
                                         if let Some(reason) = is_valid_input_value(
                                             schema,
@@ -173,7 +200,10 @@ pub fn is_valid_input_value(
                                     }
                                 }
 
-                                if let Some(name) = input_names.iter().next() {
+                                if let Some(name) = values
+                                    .keys()
+                                    .find(|name| !ty.input_fields.contains_key(*name))
+                                {
                                     return Some(valid_error(
                                         &path_node,
                                         format!(
@@ -211,9 +241,84 @@ pub fn is_valid_input_value(
     }
 }
 
+// Integers beyond the u64 range arrive already parsed as an f64, so a whole-number float is
+// treated as an out-of-range integer instead of being truncated.
+fn int_out_of_range(value: &ConstValue) -> Option<&Number> {
+    match value {
+        ConstValue::Number(n) if n.is_u64() || n.is_i64() => match n.as_i64() {
+            Some(v) if i32::MIN as i64 <= v && v <= i32::MAX as i64 => None,
+            _ => Some(n),
+        },
+        _ => id_out_of_range(value),
+    }
+}
+
+fn id_out_of_range(value: &ConstValue) -> Option<&Number> {
+    match value {
+        ConstValue::Number(n) if n.is_f64() => match n.as_f64() {
+            Some(v) if v.fract() == 0.0 => Some(n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn float_precision_loss(
+    schema: &ComposedSchema,
+    ty: &Type,
+    value: &ConstValue,
+    path_node: PathNode,
+) -> Option<String> {
+    match (&ty.base, value) {
+        (BaseType::List(element_ty), ConstValue::List(elements)) => {
+            elements.iter().enumerate().find_map(|(idx, elem)| {
+                float_precision_loss(schema, element_ty, elem, path_node.index(idx))
+            })
+        }
+        (BaseType::List(element_ty), _) => {
+            float_precision_loss(schema, element_ty, value, path_node)
+        }
+        (BaseType::Named(type_name), ConstValue::Number(n)) if type_name.as_str() == "Float" => {
+            let exact = match (n.as_i64(), n.as_u64()) {
+                (Some(v), _) => v as f64 as i64 == v,
+                (None, Some(v)) => v as f64 as u64 == v,
+                (None, None) => true,
+            };
+            if exact {
+                None
+            } else {
+                Some(valid_error(
+                    &path_node,
+                    format!("Float cannot represent {} without losing precision", n),
+                ))
+            }
+        }
+        (BaseType::Named(type_name), ConstValue::Object(values)) => {
+            let ty = schema.types.get(type_name)?;
+            ty.input_fields.values().find_map(|field| {
+                values.get(&field.name).and_then(|value| {
+                    float_precision_loss(
+                        schema,
+                        &field.ty,
+                        value,
+                        path_node.name(field.name.as_str()),
+                    )
+                })
+            })
+        }
+        _ => None,
+    }
+}
+
+// Numbers are carried as JSON numbers: integers are forwarded exactly, while decimal literals
+// have already been parsed into an f64 and keep only its precision. `float_precision_loss`
+// reports integers passed as `Float` that a subgraph could not read back exactly.
 fn is_valid_scalar_value(type_name: &str, value: &ConstValue) -> bool {
     match (type_name, value) {
-        ("Int", ConstValue::Number(n)) if n.is_i64() || n.is_u64() => true,
+        ("Int", ConstValue::Number(n)) => n
+            .as_i64()
+            .map(|n| i32::MIN as i64 <= n && n <= i32::MAX as i64)
+            .unwrap_or_default(),
         ("Float", ConstValue::Number(_)) => true,
         ("String", ConstValue::String(_)) => true,
         ("Boolean", ConstValue::Boolean(_)) => true,
@@ -1,16 +1,41 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use anyhow::{Context, Error, Result};
 use graphgate_core::{Coordinator, Response};
+use tokio::sync::Semaphore;
 use url::Url;
 use value::Variables;
 
-use crate::http::HttpTransport;
-use crate::transport::Transport;
+use crate::http::{HttpClient, HttpTransport};
+use crate::transport::{TransientError, Transport};
 use crate::wrapper::TransportWrapper;
 
+struct InFlightGuard<'a>(&'a AtomicUsize);
+
+impl<'a> InFlightGuard<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(in_flight)
+    }
+}
+
+impl<'a> Drop for InFlightGuard<'a> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+type BoxTransport = Box<dyn Transport<Error = Error>>;
+
 #[derive(Default)]
-pub struct CoordinatorImpl(HashMap<String, Box<dyn Transport<Error = Error>>>);
+pub struct CoordinatorImpl {
+    services: HashMap<String, BoxTransport>,
+    tenants: HashMap<String, HashMap<String, BoxTransport>>,
+    semaphores: HashMap<String, Semaphore>,
+    in_flight: HashMap<String, AtomicUsize>,
+}
 
 #[async_trait::async_trait]
 impl Coordinator for CoordinatorImpl {
@@ -21,27 +46,279 @@ impl Coordinator for CoordinatorImpl {
         service: &str,
         query: &str,
         variables: Variables,
+        headers: &[(String, String)],
     ) -> Result<Response, Self::Error> {
-        match self.0.get(service) {
-            Some(transport) => transport.query(query, variables).await,
-            None => anyhow::bail!("Service '{}' is not defined."),
+        self.query_tenant(None, service, query, variables, headers)
+            .await
+    }
+
+    fn has_service(&self, service: &str) -> bool {
+        self.services.contains_key(service)
+    }
+
+    fn is_retryable(&self, err: &Self::Error) -> bool {
+        err.is::<TransientError>()
+    }
+}
+
+pub struct TenantCoordinator {
+    coordinator: Arc<CoordinatorImpl>,
+    tenant: Option<String>,
+}
+
+impl TenantCoordinator {
+    pub fn new(coordinator: Arc<CoordinatorImpl>, tenant: Option<String>) -> Self {
+        Self {
+            coordinator,
+            tenant,
         }
     }
 }
 
+#[async_trait::async_trait]
+impl Coordinator for TenantCoordinator {
+    type Error = Error;
+
+    async fn query(
+        &self,
+        service: &str,
+        query: &str,
+        variables: Variables,
+        headers: &[(String, String)],
+    ) -> Result<Response, Self::Error> {
+        self.coordinator
+            .query_tenant(self.tenant.as_deref(), service, query, variables, headers)
+            .await
+    }
+
+    fn has_service(&self, service: &str) -> bool {
+        self.coordinator.has_service(service)
+    }
+
+    fn is_retryable(&self, err: &Self::Error) -> bool {
+        self.coordinator.is_retryable(err)
+    }
+}
+
 impl CoordinatorImpl {
     pub fn add(mut self, service: impl Into<String>, transport: impl Transport) -> Self {
-        self.0
-            .insert(service.into(), Box::new(TransportWrapper(transport)));
+        let service = service.into();
+        self.in_flight.entry(service.clone()).or_default();
+        self.services
+            .insert(service, Box::new(TransportWrapper(transport)));
         self
     }
 
+    pub fn add_tenant(
+        mut self,
+        tenant: impl Into<String>,
+        service: impl Into<String>,
+        transport: impl Transport,
+    ) -> Self {
+        let service = service.into();
+        self.in_flight.entry(service.clone()).or_default();
+        self.tenants
+            .entry(tenant.into())
+            .or_default()
+            .insert(service, Box::new(TransportWrapper(transport)));
+        self
+    }
+
+    pub fn add_tenant_url(
+        self,
+        tenant: impl Into<String>,
+        service: impl Into<String>,
+        url: impl AsRef<str>,
+    ) -> Result<Self> {
+        let service = service.into();
+        let transport = http_transport(&service, url.as_ref(), reqwest::Client::new())?;
+        Ok(self.add_tenant(tenant, service, transport))
+    }
+
     pub fn add_url(self, service: impl Into<String>, url: impl AsRef<str>) -> Result<Self> {
-        let parsed_url =
-            Url::parse(url.as_ref()).context(format!("Failed to parse url: {}", url.as_ref()))?;
-        match parsed_url.scheme() {
-            "http" | "https" => Ok(self.add(service, HttpTransport::new(url.as_ref()))),
-            _ => anyhow::bail!("Unknown scheme: {}", parsed_url.scheme()),
-        }
+        self.add_url_with_client(service, url, reqwest::Client::new())
+    }
+
+    pub fn add_url_with_client(
+        self,
+        service: impl Into<String>,
+        url: impl AsRef<str>,
+        client: impl HttpClient,
+    ) -> Result<Self> {
+        let service = service.into();
+        let transport = http_transport(&service, url.as_ref(), client)?;
+        Ok(self.add(service, transport))
+    }
+
+    pub fn max_in_flight(mut self, service: &str, max_in_flight: usize) -> Self {
+        self.semaphores
+            .insert(service.to_string(), Semaphore::new(max_in_flight));
+        self
+    }
+
+    pub fn in_flight(&self, service: &str) -> Option<usize> {
+        self.in_flight
+            .get(service)
+            .map(|in_flight| in_flight.load(Ordering::SeqCst))
+    }
+
+    async fn query_tenant(
+        &self,
+        tenant: Option<&str>,
+        service: &str,
+        query: &str,
+        variables: Variables,
+        headers: &[(String, String)],
+    ) -> Result<Response> {
+        let transport = match self.tenant_service(tenant, service) {
+            Some(transport) => transport,
+            None => anyhow::bail!("Service '{}' is not defined.", service),
+        };
+        let _permit = match self.semaphores.get(service) {
+            Some(semaphore) => Some(semaphore.acquire().await?),
+            None => None,
+        };
+        let _in_flight = self.in_flight.get(service).map(InFlightGuard::new);
+        transport.query(query, variables, headers).await
+    }
+
+    fn tenant_service(&self, tenant: Option<&str>, service: &str) -> Option<&BoxTransport> {
+        tenant
+            .and_then(|tenant| self.tenants.get(tenant))
+            .and_then(|services| services.get(service))
+            .or_else(|| self.services.get(service))
+    }
+}
+
+fn http_transport<C: HttpClient>(service: &str, url: &str, client: C) -> Result<HttpTransport<C>> {
+    let parsed_url = Url::parse(url).context(format!("Failed to parse url: {}", url))?;
+    match parsed_url.scheme() {
+        "http" | "https" => Ok(HttpTransport::with_client(service, url, client)),
+        _ => anyhow::bail!("Unknown scheme: {}", parsed_url.scheme()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use super::*;
+    use crate::http::tests::FakeClient;
+
+    #[test]
+    fn add_url_with_client() {
+        let client = FakeClient::new(200, r#"{"data":{"a":1}}"#);
+        let coordinator = CoordinatorImpl::default()
+            .add_url_with_client("a", "http://a/graphql", client.clone())
+            .unwrap();
+        assert!(coordinator.has_service("a"));
+        assert!(!coordinator.has_service("b"));
+        assert!(CoordinatorImpl::default()
+            .add_url_with_client("a", "ftp://a", client.clone())
+            .is_err());
+
+        let resp = coordinator
+            .query("a", "{ a }", Variables::default(), &[])
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.data, value::value!({ "a": 1 }));
+        assert_eq!(client.requests.lock().unwrap()[0].url, "http://a/graphql");
+        assert!(coordinator
+            .query("b", "{ b }", Variables::default(), &[])
+            .now_or_never()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn route_tenant_endpoints() {
+        let shared = FakeClient::new(200, r#"{"data":{"a":"shared"}}"#);
+        let tenant = FakeClient::new(200, r#"{"data":{"a":"t1"}}"#);
+        let coordinator = Arc::new(
+            CoordinatorImpl::default()
+                .add_url_with_client("a", "http://a", shared.clone())
+                .unwrap()
+                .add_url_with_client("b", "http://b", shared.clone())
+                .unwrap()
+                .add_tenant(
+                    "t1",
+                    "a",
+                    HttpTransport::with_client("a", "http://t1", tenant.clone()),
+                ),
+        );
+        let query = |coordinator: &TenantCoordinator, service: &str| {
+            coordinator
+                .query(service, "{ a }", Variables::default(), &[])
+                .now_or_never()
+                .unwrap()
+                .unwrap()
+                .data
+        };
+
+        let t1 = TenantCoordinator::new(coordinator.clone(), Some("t1".to_string()));
+        assert_eq!(query(&t1, "a"), value::value!({ "a": "t1" }));
+        assert_eq!(query(&t1, "b"), value::value!({ "a": "shared" }));
+        assert!(t1.has_service("a"));
+
+        let t2 = TenantCoordinator::new(coordinator.clone(), Some("t2".to_string()));
+        assert_eq!(query(&t2, "a"), value::value!({ "a": "shared" }));
+        let anonymous = TenantCoordinator::new(coordinator.clone(), None);
+        assert_eq!(query(&anonymous, "a"), value::value!({ "a": "shared" }));
+
+        let tenant_headers = vec![("x-tenant".to_string(), "t1".to_string())];
+        let resp = coordinator
+            .query("a", "{ a }", Variables::default(), &tenant_headers)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.data, value::value!({ "a": "shared" }));
+
+        assert_eq!(tenant.requests.lock().unwrap().len(), 1);
+        let requests = shared.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(requests
+            .iter()
+            .all(|request| request.headers.iter().all(|(name, _)| name != "x-tenant")));
+    }
+
+    #[test]
+    fn max_in_flight_shared_by_tenants() {
+        let gate = Arc::new(Semaphore::new(0));
+        let client = FakeClient {
+            gate: Some(gate.clone()),
+            ..FakeClient::new(200, r#"{"data":{}}"#)
+        };
+        let coordinator = Arc::new(
+            CoordinatorImpl::default()
+                .add_url_with_client("a", "http://a", client.clone())
+                .unwrap()
+                .add_tenant(
+                    "t1",
+                    "a",
+                    HttpTransport::with_client("a", "http://t1", client.clone()),
+                )
+                .max_in_flight("a", 1),
+        );
+        assert_eq!(coordinator.in_flight("a"), Some(0));
+        assert_eq!(coordinator.in_flight("b"), None);
+
+        let t1 = TenantCoordinator::new(coordinator.clone(), Some("t1".to_string()));
+        let mut first = coordinator.query("a", "{ a }", Variables::default(), &[]);
+        let mut second = t1.query("a", "{ a }", Variables::default(), &[]);
+        assert!((&mut first).now_or_never().is_none());
+        assert!((&mut second).now_or_never().is_none());
+        assert_eq!(coordinator.in_flight("a"), Some(1));
+        assert_eq!(client.requests.lock().unwrap().len(), 1);
+
+        gate.add_permits(1);
+        assert!(first.now_or_never().unwrap().is_ok());
+        assert_eq!(coordinator.in_flight("a"), Some(0));
+        assert!(second.now_or_never().unwrap().is_ok());
+        assert_eq!(coordinator.in_flight("a"), Some(0));
+
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].url, "http://t1");
     }
 }
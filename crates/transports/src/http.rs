@@ -1,34 +1,310 @@
-use futures_util::future::TryFutureExt;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use anyhow::{Error, Result};
 use graphgate_core::Response;
-use reqwest::Error;
-use value::{value, Variables};
+use value::{value, ConstValue, Variables};
+
+use crate::transport::{TransientError, Transport};
+
+pub struct HttpRequest {
+    pub service: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+#[async_trait::async_trait]
+pub trait HttpClient: Sync + Send + 'static {
+    type Error: Display + 'static;
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error>;
+}
+
+#[async_trait::async_trait]
+impl HttpClient for reqwest::Client {
+    type Error = reqwest::Error;
+
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
+        let mut builder = self.post(&request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let resp = builder.body(request.body).send().await?;
+        let status = resp.status().as_u16();
+        let body = resp.bytes().await?.to_vec();
+        Ok(HttpResponse { status, body })
+    }
+}
+
+pub trait ResponseDecoder: Sync + Send + 'static {
+    fn decode(&self, body: &[u8]) -> Result<Response>;
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct JsonDecoder;
+
+impl ResponseDecoder for JsonDecoder {
+    fn decode(&self, body: &[u8]) -> Result<Response> {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
 
-use crate::transport::Transport;
+pub struct ExtraKeysDecoder<F> {
+    handler: F,
+}
+
+impl<F> ExtraKeysDecoder<F>
+where
+    F: Fn(&mut Response, BTreeMap<String, ConstValue>) + Sync + Send + 'static,
+{
+    pub fn new(handler: F) -> Self {
+        Self { handler }
+    }
+}
 
-pub struct HttpTransport {
-    client: reqwest::Client,
+impl<F> ResponseDecoder for ExtraKeysDecoder<F>
+where
+    F: Fn(&mut Response, BTreeMap<String, ConstValue>) + Sync + Send + 'static,
+{
+    fn decode(&self, body: &[u8]) -> Result<Response> {
+        let mut response: Response = serde_json::from_slice(body)?;
+        let mut keys: BTreeMap<String, ConstValue> = serde_json::from_slice(body)?;
+        keys.remove("data");
+        keys.remove("errors");
+        keys.remove("extensions");
+        if !keys.is_empty() {
+            (self.handler)(&mut response, keys);
+        }
+        Ok(response)
+    }
+}
+
+pub struct HttpTransport<C = reqwest::Client, D = JsonDecoder> {
+    client: C,
+    decoder: D,
+    service: String,
     url: String,
 }
 
 impl HttpTransport {
-    pub fn new(url: impl Into<String>) -> Self {
+    pub fn new(service: impl Into<String>, url: impl Into<String>) -> Self {
+        Self::with_client(service, url, reqwest::Client::new())
+    }
+}
+
+impl<C: HttpClient> HttpTransport<C> {
+    pub fn with_client(service: impl Into<String>, url: impl Into<String>, client: C) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
+            decoder: JsonDecoder,
+            service: service.into(),
             url: url.into(),
         }
     }
 }
 
+impl<C: HttpClient, D: ResponseDecoder> HttpTransport<C, D> {
+    pub fn decoder<D2: ResponseDecoder>(self, decoder: D2) -> HttpTransport<C, D2> {
+        HttpTransport {
+            client: self.client,
+            decoder,
+            service: self.service,
+            url: self.url,
+        }
+    }
+}
+
 #[async_trait::async_trait]
-impl Transport for HttpTransport {
+impl<C: HttpClient, D: ResponseDecoder> Transport for HttpTransport<C, D> {
     type Error = Error;
 
-    async fn query(&self, query: &str, variables: Variables) -> Result<Response, Self::Error> {
-        self.client
-            .post(&self.url)
-            .json(&value!({ "query": query, "variables": variables }))
-            .send()
-            .and_then(|resp| resp.json::<Response>())
+    async fn query(
+        &self,
+        query: &str,
+        variables: Variables,
+        headers: &[(String, String)],
+    ) -> Result<Response, Self::Error> {
+        let body = serde_json::to_vec(&value!({ "query": query, "variables": variables }))?;
+        let mut request_headers =
+            vec![("content-type".to_string(), "application/json".to_string())];
+        request_headers.extend(headers.iter().cloned());
+        let resp = self
+            .client
+            .send(HttpRequest {
+                service: self.service.clone(),
+                url: self.url.clone(),
+                headers: request_headers,
+                body,
+            })
             .await
+            .map_err(|err| TransientError(err.to_string()))?;
+        if !(200..300).contains(&resp.status) {
+            let message = format!("Unexpected HTTP status: {}.", resp.status);
+            return Err(match resp.status {
+                429 | 502 | 503 | 504 => Error::new(TransientError(message)),
+                _ => anyhow::anyhow!(message),
+            });
+        }
+        self.decoder.decode(&resp.body)
+    }
+
+    fn is_retryable(&self, err: &Self::Error) -> bool {
+        err.is::<TransientError>()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::FutureExt;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+
+    #[derive(Clone)]
+    pub(crate) struct FakeClient {
+        pub(crate) status: u16,
+        pub(crate) body: &'static str,
+        pub(crate) gate: Option<Arc<Semaphore>>,
+        pub(crate) requests: Arc<Mutex<Vec<HttpRequest>>>,
+    }
+
+    impl FakeClient {
+        pub(crate) fn new(status: u16, body: &'static str) -> Self {
+            Self {
+                status,
+                body,
+                gate: None,
+                requests: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for FakeClient {
+        type Error = String;
+
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Self::Error> {
+            self.requests.lock().unwrap().push(request);
+            if let Some(gate) = &self.gate {
+                let _ = gate.acquire().await.map_err(|err| err.to_string())?;
+            }
+            Ok(HttpResponse {
+                status: self.status,
+                body: self.body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn send_with_client() {
+        let client = FakeClient::new(200, r#"{"data":{"me":1}}"#);
+        let transport =
+            HttpTransport::with_client("accounts", "http://accounts/graphql", client.clone());
+        let headers = vec![("x-request-id".to_string(), "1".to_string())];
+        let resp = transport
+            .query("{ me }", Variables::default(), &headers)
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.data, value!({ "me": 1 }));
+
+        let requests = client.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].service, "accounts");
+        assert_eq!(requests[0].url, "http://accounts/graphql");
+        assert_eq!(
+            requests[0].headers,
+            vec![
+                ("content-type".to_string(), "application/json".to_string()),
+                ("x-request-id".to_string(), "1".to_string()),
+            ]
+        );
+        let body: ConstValue = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body, value!({ "query": "{ me }", "variables": {} }));
+    }
+
+    #[test]
+    fn retryable_status() {
+        for (status, retryable) in &[(503, true), (429, true), (400, false), (500, false)] {
+            let transport = HttpTransport::with_client(
+                "accounts",
+                "http://accounts",
+                FakeClient::new(*status, ""),
+            );
+            let err = transport
+                .query("{ me }", Variables::default(), &[])
+                .now_or_never()
+                .unwrap()
+                .unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("Unexpected HTTP status: {}.", status)
+            );
+            assert_eq!(transport.is_retryable(&err), *retryable);
+        }
+    }
+
+    #[test]
+    fn json_decoder() {
+        let resp = JsonDecoder
+            .decode(br#"{"data":{"a":1},"errors":[{"message":"x","extensions":{"code":"E"}}],"meta":1}"#)
+            .unwrap();
+        assert_eq!(resp.data, value!({ "a": 1 }));
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "x");
+        assert_eq!(resp.errors[0].extensions.get("code"), Some(&value!("E")));
+        assert!(resp.extensions.is_empty());
+        assert!(JsonDecoder.decode(b"not json").is_err());
+    }
+
+    #[test]
+    fn extra_keys_decoder() {
+        let calls = Arc::new(Mutex::new(0));
+        let decoder = ExtraKeysDecoder::new({
+            let calls = calls.clone();
+            move |resp: &mut Response, keys: BTreeMap<String, ConstValue>| {
+                *calls.lock().unwrap() += 1;
+                resp.extensions.extend(keys);
+            }
+        });
+
+        let resp = decoder
+            .decode(br#"{"data":{"a":1},"extensions":{"b":2},"meta":{"c":3}}"#)
+            .unwrap();
+        assert_eq!(resp.data, value!({ "a": 1 }));
+        assert_eq!(resp.extensions.get("b"), Some(&value!(2)));
+        assert_eq!(resp.extensions.get("meta"), Some(&value!({ "c": 3 })));
+        assert_eq!(*calls.lock().unwrap(), 1);
+
+        decoder.decode(br#"{"data":{"a":1}}"#).unwrap();
+        assert_eq!(*calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn transport_with_decoder() {
+        let transport = HttpTransport::with_client(
+            "accounts",
+            "http://accounts",
+            FakeClient::new(200, r#"{"data":{"a":1},"meta":2}"#),
+        )
+        .decoder(ExtraKeysDecoder::new(
+            |resp: &mut Response, keys: BTreeMap<String, ConstValue>| {
+                resp.extensions.extend(keys);
+            },
+        ));
+        let resp = transport
+            .query("{ a }", Variables::default(), &[])
+            .now_or_never()
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.extensions.get("meta"), Some(&value!(2)));
     }
 }
@@ -3,4 +3,9 @@ mod http;
 mod transport;
 mod wrapper;
 
-pub use coordinator::CoordinatorImpl;
+pub use coordinator::{CoordinatorImpl, TenantCoordinator};
+pub use http::{
+    ExtraKeysDecoder, HttpClient, HttpRequest, HttpResponse, HttpTransport, JsonDecoder,
+    ResponseDecoder,
+};
+pub use transport::Transport;
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::fmt::{self, Display};
 
 use graphgate_core::Response;
 use value::Variables;
@@ -7,5 +7,25 @@ use value::Variables;
 pub trait Transport: Sync + Send + 'static {
     type Error: Display + 'static;
 
-    async fn query(&self, query: &str, variables: Variables) -> Result<Response, Self::Error>;
+    async fn query(
+        &self,
+        query: &str,
+        variables: Variables,
+        headers: &[(String, String)],
+    ) -> Result<Response, Self::Error>;
+
+    fn is_retryable(&self, _err: &Self::Error) -> bool {
+        false
+    }
 }
+
+#[derive(Debug)]
+pub(crate) struct TransientError(pub(crate) String);
+
+impl Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
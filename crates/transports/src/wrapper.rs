@@ -2,7 +2,7 @@ use anyhow::Error;
 use graphgate_core::Response;
 use value::Variables;
 
-use crate::transport::Transport;
+use crate::transport::{TransientError, Transport};
 
 pub struct TransportWrapper<T>(pub T);
 
@@ -10,10 +10,25 @@ pub struct TransportWrapper<T>(pub T);
 impl<T: Transport> Transport for TransportWrapper<T> {
     type Error = Error;
 
-    async fn query(&self, query: &str, variables: Variables) -> Result<Response, Self::Error> {
+    async fn query(
+        &self,
+        query: &str,
+        variables: Variables,
+        headers: &[(String, String)],
+    ) -> Result<Response, Self::Error> {
         self.0
-            .query(query, variables)
+            .query(query, variables, headers)
             .await
-            .map_err(|err| anyhow::anyhow!("{}", err))
+            .map_err(|err| {
+                if self.0.is_retryable(&err) {
+                    Error::new(TransientError(err.to_string()))
+                } else {
+                    anyhow::anyhow!("{}", err)
+                }
+            })
+    }
+
+    fn is_retryable(&self, err: &Self::Error) -> bool {
+        err.is::<TransientError>()
     }
 }
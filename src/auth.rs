@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use warp::http::HeaderMap;
+
+pub struct RequestContext<'a> {
+    pub headers: &'a HeaderMap,
+}
+
+impl<'a> RequestContext<'a> {
+    pub fn new(headers: &'a HeaderMap) -> Self {
+        Self { headers }
+    }
+
+    fn authorization(&self) -> Option<&'a str> {
+        self.headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn bearer_token(&self) -> Option<&'a str> {
+        let authorization = self.authorization()?;
+        let (scheme, token) = authorization.split_at(authorization.find(' ')?);
+        if scheme.eq_ignore_ascii_case("bearer") {
+            Some(token.trim()).filter(|token| !token.is_empty())
+        } else {
+            None
+        }
+    }
+}
+
+pub trait AuthGate: Send + Sync {
+    fn is_authenticated(&self, ctx: &RequestContext<'_>) -> bool;
+}
+
+impl<F> AuthGate for F
+where
+    F: Fn(&RequestContext<'_>) -> bool + Send + Sync,
+{
+    fn is_authenticated(&self, ctx: &RequestContext<'_>) -> bool {
+        self(ctx)
+    }
+}
+
+pub trait AuthzValidator: Send + Sync {
+    fn is_authorized(&self, ctx: &RequestContext<'_>) -> bool;
+}
+
+impl<F> AuthzValidator for F
+where
+    F: Fn(&RequestContext<'_>) -> bool + Send + Sync,
+{
+    fn is_authorized(&self, ctx: &RequestContext<'_>) -> bool {
+        self(ctx)
+    }
+}
+
+pub trait TenantResolver: Send + Sync {
+    fn tenant(&self, ctx: &RequestContext<'_>) -> Option<String>;
+}
+
+impl<F> TenantResolver for F
+where
+    F: Fn(&RequestContext<'_>) -> Option<String> + Send + Sync,
+{
+    fn tenant(&self, ctx: &RequestContext<'_>) -> Option<String> {
+        self(ctx)
+    }
+}
+
+pub struct AuthorizationHeaderPresent;
+
+impl AuthGate for AuthorizationHeaderPresent {
+    fn is_authenticated(&self, ctx: &RequestContext<'_>) -> bool {
+        ctx.authorization().is_some()
+    }
+}
+
+pub struct BearerTokens {
+    tokens: HashSet<String>,
+}
+
+impl BearerTokens {
+    pub fn new(tokens: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            tokens: tokens.into_iter().collect(),
+        }
+    }
+
+    fn contains(&self, ctx: &RequestContext<'_>) -> bool {
+        ctx.bearer_token()
+            .map(|token| self.tokens.contains(token))
+            .unwrap_or_default()
+    }
+}
+
+impl AuthGate for BearerTokens {
+    fn is_authenticated(&self, ctx: &RequestContext<'_>) -> bool {
+        self.contains(ctx)
+    }
+}
+
+impl AuthzValidator for BearerTokens {
+    fn is_authorized(&self, ctx: &RequestContext<'_>) -> bool {
+        self.contains(ctx)
+    }
+}
+
+pub struct BearerTokenTenants {
+    tenants: HashMap<String, String>,
+}
+
+impl BearerTokenTenants {
+    pub fn new(tenants: HashMap<String, String>) -> Self {
+        Self { tenants }
+    }
+}
+
+impl TenantResolver for BearerTokenTenants {
+    fn tenant(&self, ctx: &RequestContext<'_>) -> Option<String> {
+        self.tenants.get(ctx.bearer_token()?).cloned()
+    }
+}
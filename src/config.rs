@@ -1,11 +1,33 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::Result;
+use graphgate_core::{ExecutorOptions, ListMergePolicy, RetryBudget, RetryPolicy, ServiceOptions};
 use graphgate_transports::CoordinatorImpl;
 use serde::{Deserialize, Serialize};
 
+use crate::auth::{
+    AuthGate, AuthorizationHeaderPresent, AuthzValidator, BearerTokenTenants, BearerTokens,
+    TenantResolver,
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServiceConfig {
     pub name: String,
     pub url: String,
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub strict_entities: bool,
+    #[serde(default)]
+    pub max_in_flight: Option<usize>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub tenant_urls: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,6 +35,70 @@ pub struct Config {
     pub services: Vec<ServiceConfig>,
     #[serde(default = "default_bind")]
     pub bind: String,
+    #[serde(default)]
+    pub expose_cost: bool,
+    #[serde(default)]
+    pub plan_warnings: bool,
+    #[serde(default)]
+    pub expose_sources: bool,
+    #[serde(default)]
+    pub max_variables: Option<usize>,
+    #[serde(default)]
+    pub parse_error_source: bool,
+    #[serde(default)]
+    pub max_fragments: Option<usize>,
+    #[serde(default)]
+    pub max_selection_width: Option<usize>,
+    #[serde(default)]
+    pub max_root_fields: Option<usize>,
+    #[serde(default)]
+    pub max_complexity: Option<usize>,
+    #[serde(default)]
+    pub tenant_complexity: HashMap<String, usize>,
+    #[serde(default)]
+    pub tenant_tokens: HashMap<String, String>,
+    #[serde(default)]
+    pub max_request_bytes: Option<usize>,
+    #[serde(default)]
+    pub list_merge_policy: ListMergePolicy,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub total_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    #[serde(default)]
+    pub max_representations: Option<usize>,
+    #[serde(default)]
+    pub max_introspection_depth: Option<usize>,
+    #[serde(default)]
+    pub plan_cache_size: Option<usize>,
+    #[serde(default)]
+    pub fetch_cache_size: Option<usize>,
+    #[serde(default)]
+    pub forward_headers: Vec<String>,
+    #[serde(default)]
+    pub retry_max_attempts: Option<usize>,
+    #[serde(default)]
+    pub retry_base_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub retry_max_delay_ms: Option<u64>,
+    #[serde(default)]
+    pub retry_budget_ratio: Option<f64>,
+    #[serde(default)]
+    pub retry_budget_min_per_second: Option<f64>,
+    #[serde(default)]
+    pub require_authentication: bool,
+    #[serde(default)]
+    pub auth_tokens: Vec<String>,
+    #[serde(default)]
+    pub authz_tokens: Vec<String>,
+    #[serde(default)]
+    pub allow_unauthenticated_introspection: bool,
+    #[serde(default = "default_introspection_enabled")]
+    pub introspection_enabled: bool,
+    #[serde(default)]
+    pub allow_empty_responses: bool,
 }
 
 impl Config {
@@ -20,11 +106,101 @@ impl Config {
         let mut coordinator = CoordinatorImpl::default();
         for service in &self.services {
             coordinator = coordinator.add_url(&service.name, &service.url)?;
+            if let Some(max_in_flight) = service.max_in_flight {
+                coordinator = coordinator.max_in_flight(&service.name, max_in_flight);
+            }
+            for (tenant, url) in &service.tenant_urls {
+                coordinator = coordinator.add_tenant_url(tenant, &service.name, url)?;
+            }
         }
         Ok(coordinator)
     }
+
+    pub fn create_executor_options(&self) -> ExecutorOptions {
+        let mut options = ExecutorOptions {
+            max_request_bytes: self.max_request_bytes,
+            list_merge_policy: self.list_merge_policy,
+            timeout: self.timeout_ms.map(Duration::from_millis),
+            total_timeout: self.total_timeout_ms.map(Duration::from_millis),
+            expose_sources: self.expose_sources,
+            max_concurrency: self.max_concurrency,
+            max_representations: self.max_representations,
+            max_introspection_depth: self.max_introspection_depth,
+            introspection_enabled: self.introspection_enabled,
+            allow_empty_responses: self.allow_empty_responses,
+            forward_headers: self.forward_headers.clone(),
+            retry: self.retry_max_attempts.map(|max_attempts| {
+                let mut retry = RetryPolicy {
+                    max_attempts,
+                    ..Default::default()
+                };
+                if let Some(base_delay_ms) = self.retry_base_delay_ms {
+                    retry.base_delay = Duration::from_millis(base_delay_ms);
+                }
+                if let Some(max_delay_ms) = self.retry_max_delay_ms {
+                    retry.max_delay = Duration::from_millis(max_delay_ms);
+                }
+                retry
+            }),
+            ..Default::default()
+        };
+        for service in &self.services {
+            options = options.service(
+                &service.name,
+                ServiceOptions {
+                    max_body_size: service.max_body_size,
+                    optional: service.optional,
+                    strict_entities: service.strict_entities,
+                    timeout: service.timeout_ms.map(Duration::from_millis),
+                },
+            );
+        }
+        options
+    }
+
+    pub fn create_retry_budget(&self) -> Option<Arc<RetryBudget>> {
+        self.retry_budget_ratio.map(|ratio| {
+            let mut retry_budget = RetryBudget::new(ratio);
+            if let Some(min_per_second) = self.retry_budget_min_per_second {
+                retry_budget = retry_budget.min_per_second(min_per_second);
+            }
+            Arc::new(retry_budget)
+        })
+    }
+
+    pub fn create_auth_gate(&self) -> Option<Arc<dyn AuthGate>> {
+        if !self.require_authentication {
+            None
+        } else if self.auth_tokens.is_empty() {
+            Some(Arc::new(AuthorizationHeaderPresent))
+        } else {
+            Some(Arc::new(BearerTokens::new(self.auth_tokens.clone())))
+        }
+    }
+
+    pub fn create_authz_validator(&self) -> Option<Arc<dyn AuthzValidator>> {
+        if self.authz_tokens.is_empty() {
+            None
+        } else {
+            Some(Arc::new(BearerTokens::new(self.authz_tokens.clone())))
+        }
+    }
+
+    pub fn create_tenant_resolver(&self) -> Option<Arc<dyn TenantResolver>> {
+        if self.tenant_tokens.is_empty() {
+            None
+        } else {
+            Some(Arc::new(BearerTokenTenants::new(
+                self.tenant_tokens.clone(),
+            )))
+        }
+    }
 }
 
 fn default_bind() -> String {
     "127.0.0.1:8000".to_string()
 }
+
+fn default_introspection_enabled() -> bool {
+    true
+}
@@ -1,24 +1,44 @@
+mod auth;
 mod config;
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Error, Result};
 use clap::{crate_version, App, Arg};
-use graphgate_core::{ComposedSchema, Coordinator, Executor, PlanBuilder};
-use graphgate_transports::CoordinatorImpl;
+use futures_util::StreamExt;
+use graphgate_core::{
+    ComposedSchema, Coordinator, Executor, ExecutorOptions, IncrementalResponse, MemoryFetchCache,
+    PlanBuilder, PlanCache, Response, ServerError,
+};
+use graphgate_transports::{CoordinatorImpl, TenantCoordinator};
 use serde::Deserialize;
 use tokio::sync::Mutex;
 use tokio::time::Duration;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::{fmt, EnvFilter};
-use value::Variables;
-use warp::http::{Response as HttpResponse, StatusCode};
-use warp::Filter;
+use value::{ConstValue, Variables};
+use warp::http::{HeaderMap, Response as HttpResponse, StatusCode};
+use warp::hyper::Body;
+use warp::{Filter, Reply};
 
+use auth::{AuthGate, AuthzValidator, RequestContext, TenantResolver};
 use config::{Config, ServiceConfig};
 
-type SharedComposedSchema = Arc<Mutex<Option<Arc<ComposedSchema>>>>;
+type SharedComposedSchema = Arc<Mutex<Option<SchemaState>>>;
+
+const MULTIPART_BOUNDARY: &str = "-";
+
+#[derive(Clone)]
+struct SchemaState {
+    composed_schema: Arc<ComposedSchema>,
+    plan_cache: Option<Arc<PlanCache>>,
+}
 
 #[derive(Debug, Deserialize)]
 struct Request {
@@ -60,6 +80,7 @@ async fn main() -> Result<()> {
         shared_composed_schema.clone(),
         coordinator.clone(),
         config.services.clone(),
+        config.plan_cache_size,
     );
     serve(config, shared_composed_schema.clone(), coordinator.clone()).await?;
     Ok(())
@@ -80,12 +101,31 @@ fn start_update_schema_loop(
     shared_composed_schema: SharedComposedSchema,
     coordinator: Arc<CoordinatorImpl>,
     services: Vec<ServiceConfig>,
+    plan_cache_size: Option<usize>,
 ) {
     tokio::spawn(async move {
+        let mut schema_hash = None;
         loop {
             tracing::debug!("Update schema.");
-            match update_schema(&coordinator, &services).await {
-                Ok(schema) => *shared_composed_schema.lock().await = Some(Arc::new(schema)),
+            match fetch_sdl(&coordinator, &services).await {
+                Ok(sdl) => {
+                    let mut hasher = DefaultHasher::new();
+                    sdl.hash(&mut hasher);
+                    let hash = hasher.finish();
+                    if schema_hash != Some(hash) {
+                        match compose_schema(sdl) {
+                            Ok(schema) => {
+                                *shared_composed_schema.lock().await = Some(SchemaState {
+                                    composed_schema: Arc::new(schema),
+                                    plan_cache: plan_cache_size
+                                        .map(|size| Arc::new(PlanCache::new(size))),
+                                });
+                                schema_hash = Some(hash);
+                            }
+                            Err(err) => tracing::error!(error = %err, "Failed to update schema"),
+                        }
+                    }
+                }
                 Err(err) => tracing::error!(error = %err, "Failed to update schema"),
             }
             tokio::time::sleep(Duration::from_secs(30)).await;
@@ -93,10 +133,10 @@ fn start_update_schema_loop(
     });
 }
 
-async fn update_schema(
+async fn fetch_sdl(
     coordinator: &impl Coordinator<Error = Error>,
     services: &[ServiceConfig],
-) -> Result<ComposedSchema> {
+) -> Result<Vec<(String, String)>> {
     const QUERY_SDL: &str = "{ _service { sdl }}";
 
     #[derive(Deserialize)]
@@ -110,20 +150,28 @@ async fn update_schema(
         sdl: String,
     }
 
-    let resp = futures_util::future::try_join_all(services.iter().map(|service| async move {
+    futures_util::future::try_join_all(services.iter().map(|service| async move {
         let resp = coordinator
-            .query(&service.name, QUERY_SDL, Default::default())
+            .query(&service.name, QUERY_SDL, Default::default(), &[])
             .await
             .context(format!("Failed to fetch SDL from '{}'.", service.name))?;
         let resp: ResponseQuery =
             value::from_value(resp.data).context("Failed to parse response.")?;
-        let document = parser::parse_schema(resp.service.sdl)
-            .context(format!("Invalid SDL from '{}'.", service.name))?;
-        Ok::<_, Error>((service.name.clone(), document))
+        Ok::<_, Error>((service.name.clone(), resp.service.sdl))
     }))
-    .await?;
+    .await
+}
 
-    Ok(ComposedSchema::combine(resp).context("Unable to merge schema.")?)
+fn compose_schema(sdl: Vec<(String, String)>) -> Result<ComposedSchema> {
+    let documents = sdl
+        .into_iter()
+        .map(|(service, sdl)| {
+            let document =
+                parser::parse_schema(sdl).context(format!("Invalid SDL from '{}'.", service))?;
+            Ok((service, document))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ComposedSchema::combine(documents).context("Unable to merge schema.")?)
 }
 
 async fn serve(
@@ -135,54 +183,218 @@ async fn serve(
         .bind
         .parse()
         .context(format!("Failed to parse bind addr '{}'.", config.bind))?;
+    let executor_options = Arc::new(config.create_executor_options());
+    let fetch_cache = config
+        .fetch_cache_size
+        .map(|size| Arc::new(MemoryFetchCache::new(size)));
+    let retry_budget = config.create_retry_budget();
+    let expose_cost = config.expose_cost;
+    let plan_warnings = config.plan_warnings;
+    let max_variables = config.max_variables;
+    let parse_error_source = config.parse_error_source;
+    let max_fragments = config.max_fragments;
+    let max_selection_width = config.max_selection_width;
+    let max_root_fields = config.max_root_fields;
+    let max_complexity = config.max_complexity;
+    let tenant_resolver = config.create_tenant_resolver();
+    let tenant_complexity = Arc::new(config.tenant_complexity.clone());
+    let auth_gate = config.create_auth_gate();
+    let authz_validator = config.create_authz_validator();
+    let allow_unauthenticated_introspection = config.allow_unauthenticated_introspection;
 
     let graphql = warp::path::end()
         .and(warp::post())
         .and(warp::body::json())
+        .and(warp::header::optional::<String>("x-request-id"))
+        .and(warp::header::headers_cloned())
         .and_then({
             let shared_composed_schema = shared_composed_schema.clone();
             let coordinator = coordinator.clone();
-            move |request: Request| {
+            move |request: Request, request_id: Option<String>, headers: HeaderMap| {
                 let shared_composed_schema = shared_composed_schema.clone();
                 let coordinator = coordinator.clone();
+                let executor_options = executor_options.clone();
+                let fetch_cache = fetch_cache.clone();
+                let retry_budget = retry_budget.clone();
+                let tenant_resolver = tenant_resolver.clone();
+                let tenant_complexity = tenant_complexity.clone();
+                let auth_gate = auth_gate.clone();
+                let authz_validator = authz_validator.clone();
                 async move {
-                    let composed_schema = {
+                    let trace_id = request_id
+                        .filter(|request_id| !request_id.trim().is_empty())
+                        .unwrap_or_else(generate_trace_id);
+                    let SchemaState {
+                        composed_schema,
+                        plan_cache,
+                    } = {
                         let shared_composed_schema = shared_composed_schema.lock().await;
                         match &*shared_composed_schema {
-                            Some(composed_schema) => composed_schema.clone(),
+                            Some(schema_state) => schema_state.clone(),
                             None => {
                                 return Ok(HttpResponse::builder()
                                     .status(StatusCode::SERVICE_UNAVAILABLE)
-                                    .body("Gateway is not ready.".to_string()));
+                                    .body("Gateway is not ready.".to_string())
+                                    .into_response());
                             }
                         }
                     };
-                    let document = match parser::parse_query(request.query) {
+                    let document = match parser::parse_query(&request.query) {
                         Ok(document) => document,
                         Err(err) => {
-                            return Ok(HttpResponse::builder()
-                                .status(StatusCode::BAD_REQUEST)
-                                .body(err.to_string()));
+                            let response =
+                                parse_error_response(&request.query, &err, parse_error_source);
+                            return Ok(graphql_response(response, &trace_id).into_response());
                         }
                     };
-                    let mut plan_builder =
-                        PlanBuilder::new(&composed_schema, document).variables(request.variables);
+                    let request_context = RequestContext::new(&headers);
+                    let authorized = authz_validator
+                        .as_ref()
+                        .map(|authz_validator| authz_validator.is_authorized(&request_context))
+                        .unwrap_or_default();
+                    let mut plan_builder = PlanBuilder::new(&composed_schema, document)
+                        .variables(request.variables)
+                        .authorized(authorized);
                     if let Some(operation) = request.operation {
                         plan_builder = plan_builder.operation_name(operation);
                     }
-                    let plan = match plan_builder.plan() {
-                        Ok(plan) => plan,
-                        Err(response) => {
-                            return Ok(HttpResponse::builder()
-                                .status(StatusCode::OK)
-                                .body(serde_json::to_string(&response).unwrap()))
+                    if let Some(max_variables) = max_variables {
+                        plan_builder = plan_builder.max_variables(max_variables);
+                    }
+                    if let Some(max_fragments) = max_fragments {
+                        plan_builder = plan_builder.max_fragments(max_fragments);
+                    }
+                    if let Some(max_selection_width) = max_selection_width {
+                        plan_builder = plan_builder.max_selection_width(max_selection_width);
+                    }
+                    if let Some(max_root_fields) = max_root_fields {
+                        plan_builder = plan_builder.max_root_fields(max_root_fields);
+                    }
+                    let tenant = tenant_resolver
+                        .as_ref()
+                        .and_then(|tenant_resolver| tenant_resolver.tenant(&request_context));
+                    let tenant_budget = tenant
+                        .as_ref()
+                        .and_then(|tenant| tenant_complexity.get(tenant).copied());
+                    if let Some(max_complexity) = tenant_budget.or(max_complexity) {
+                        plan_builder = plan_builder.max_complexity(max_complexity);
+                    }
+                    let authenticated = auth_gate
+                        .as_ref()
+                        .map(|auth_gate| auth_gate.is_authenticated(&request_context))
+                        .unwrap_or(true);
+                    if !authenticated
+                        && !(allow_unauthenticated_introspection && is_introspection(&plan_builder))
+                    {
+                        return Ok(
+                            graphql_response(unauthenticated_response(), &trace_id).into_response()
+                        );
+                    }
+                    let plan_key = plan_cache
+                        .as_ref()
+                        .map(|_| plan_builder.cache_key(&request.query));
+                    let cached_plan = plan_cache
+                        .as_ref()
+                        .zip(plan_key.as_ref())
+                        .and_then(|(plan_cache, plan_key)| plan_cache.get(plan_key));
+                    let plan = match cached_plan {
+                        Some(plan) => plan,
+                        None => {
+                            let plan = match plan_builder.plan() {
+                                Ok(plan) => plan,
+                                Err(response) => {
+                                    return Ok(graphql_response(response, &trace_id).into_response())
+                                }
+                            };
+                            match plan_cache.as_ref().zip(plan_key) {
+                                Some((plan_cache, plan_key)) => plan_cache.insert(plan_key, plan),
+                                None => Arc::new(plan.into_owned()),
+                            }
                         }
                     };
-                    let executor = Executor::new(&composed_schema, coordinator);
-                    Ok::<_, std::convert::Infallible>(
-                        HttpResponse::builder()
+                    let cost = if expose_cost {
+                        plan_builder.complexity()
+                    } else {
+                        None
+                    };
+                    let coordinator = TenantCoordinator::new(coordinator, tenant);
+                    let options = ExecutorOptions::clone(&executor_options);
+                    let request_headers = headers
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((name.to_string(), value.to_str().ok()?.to_string()))
+                        })
+                        .collect::<Vec<_>>();
+                    let mut extensions = BTreeMap::new();
+                    if let Some(cost) = cost {
+                        extensions
+                            .insert("cost".to_string(), ConstValue::Number((cost as u64).into()));
+                    }
+                    if plan_warnings {
+                        let mut warnings = plan_builder.warnings();
+                        warnings.extend(plan.warnings());
+                        if !warnings.is_empty() {
+                            extensions.insert(
+                                "warnings".to_string(),
+                                ConstValue::List(
+                                    warnings.into_iter().map(ConstValue::String).collect(),
+                                ),
+                            );
+                        }
+                    }
+
+                    if plan.is_deferred() && accepts_multipart(&headers) {
+                        let (mut sender, body) = Body::channel();
+                        let response = HttpResponse::builder()
                             .status(StatusCode::OK)
-                            .body(serde_json::to_string(&executor.execute(&plan).await).unwrap()),
+                            .header(
+                                "content-type",
+                                format!("multipart/mixed; boundary=\"{}\"", MULTIPART_BOUNDARY),
+                            )
+                            .header("x-request-id", trace_id.as_str())
+                            .body(body);
+                        tokio::spawn(async move {
+                            let mut executor = Executor::new(&composed_schema, coordinator)
+                                .options(options)
+                                .headers(request_headers);
+                            if let Some(fetch_cache) = fetch_cache {
+                                executor = executor.fetch_cache(fetch_cache);
+                            }
+                            if let Some(retry_budget) = retry_budget {
+                                executor = executor.retry_budget(retry_budget);
+                            }
+                            let mut responses = executor.execute_stream(&plan);
+                            let mut extensions = Some(extensions);
+                            while let Some(mut response) = responses.next().await {
+                                if let Some(extensions) = extensions.take() {
+                                    response.response.extensions.extend(extensions);
+                                }
+                                attach_trace_id(&mut response.response, &trace_id);
+                                let part = multipart_part(&response);
+                                if sender.send_data(part.into()).await.is_err() {
+                                    return;
+                                }
+                            }
+                            let _ = sender
+                                .send_data(format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY).into())
+                                .await;
+                        });
+                        return Ok(response.into_response());
+                    }
+
+                    let mut executor = Executor::new(&composed_schema, coordinator)
+                        .options(options)
+                        .headers(request_headers);
+                    if let Some(fetch_cache) = fetch_cache {
+                        executor = executor.fetch_cache(fetch_cache);
+                    }
+                    if let Some(retry_budget) = retry_budget {
+                        executor = executor.retry_budget(retry_budget);
+                    }
+                    let mut response = executor.execute(&plan).await;
+                    response.extensions.extend(extensions);
+                    Ok::<_, std::convert::Infallible>(
+                        graphql_response(response, &trace_id).into_response(),
                     )
                 }
             }
@@ -199,3 +411,133 @@ async fn serve(
     warp::serve(routes).run(bind_addr).await;
     Ok(())
 }
+
+fn is_introspection(plan_builder: &PlanBuilder<'_>) -> bool {
+    plan_builder
+        .operation_info()
+        .map(|info| {
+            info.root_fields
+                .iter()
+                .all(|field_name| field_name.starts_with("__"))
+        })
+        .unwrap_or_default()
+}
+
+fn unauthenticated_response() -> Response {
+    let mut extensions = BTreeMap::new();
+    extensions.insert(
+        "code".to_string(),
+        ConstValue::String("UNAUTHENTICATED".to_string()),
+    );
+    Response {
+        data: ConstValue::Null,
+        errors: vec![ServerError {
+            message: "Authentication is required.".to_string(),
+            locations: Default::default(),
+            path: Default::default(),
+            extensions,
+        }],
+        extensions: Default::default(),
+    }
+}
+
+fn generate_trace_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default();
+    format!(
+        "{:016x}{:08x}",
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed) as u32
+    )
+}
+
+fn accepts_multipart(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("accept")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("multipart/mixed"))
+}
+
+fn multipart_part(response: &IncrementalResponse) -> String {
+    format!(
+        "\r\n--{}\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}",
+        MULTIPART_BOUNDARY,
+        serde_json::to_string(response).unwrap()
+    )
+}
+
+fn attach_trace_id(response: &mut Response, trace_id: &str) {
+    for err in &mut response.errors {
+        tracing::warn!(trace_id, error = %err.message, "Request error");
+        err.extensions.insert(
+            "traceId".to_string(),
+            ConstValue::String(trace_id.to_string()),
+        );
+    }
+    response.extensions.insert(
+        "traceId".to_string(),
+        ConstValue::String(trace_id.to_string()),
+    );
+}
+
+fn graphql_response(
+    mut response: Response,
+    trace_id: &str,
+) -> warp::http::Result<HttpResponse<String>> {
+    attach_trace_id(&mut response, trace_id);
+    HttpResponse::builder()
+        .status(StatusCode::OK)
+        .header("x-request-id", trace_id)
+        .body(serde_json::to_string(&response).unwrap())
+}
+
+fn parse_error_response(query: &str, err: &parser::Error, render_source: bool) -> Response {
+    let locations = err.positions().collect::<Vec<_>>();
+    let mut extensions = BTreeMap::new();
+    extensions.insert(
+        "code".to_string(),
+        ConstValue::String("GRAPHQL_PARSE_FAILED".to_string()),
+    );
+    if render_source {
+        if let Some(snippet) = locations
+            .first()
+            .and_then(|pos| source_snippet(query, pos.line, pos.column))
+        {
+            extensions.insert("source".to_string(), ConstValue::String(snippet));
+        }
+    }
+    Response {
+        data: ConstValue::Null,
+        errors: vec![ServerError {
+            message: err.to_string(),
+            locations,
+            path: Default::default(),
+            extensions,
+        }],
+        extensions: Default::default(),
+    }
+}
+
+fn source_snippet(query: &str, line: usize, column: usize) -> Option<String> {
+    let source_line = query.lines().nth(line.checked_sub(1)?)?;
+    let line_number = line.to_string();
+    let gutter = " ".repeat(line_number.len());
+    let indent = source_line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    Some(format!(
+        "{gutter}--> {line}:{column}\n{gutter} |\n{line_number} | {source_line}\n{gutter} | {indent}^",
+        gutter = gutter,
+        line = line,
+        column = column,
+        line_number = line_number,
+        source_line = source_line,
+        indent = indent,
+    ))
+}